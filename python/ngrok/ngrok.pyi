@@ -1,8 +1,25 @@
-from typing import Union, Optional, Any, Awaitable, List, Mapping, Callable
+import asyncio
+from typing import (
+    Union,
+    Optional,
+    Any,
+    Awaitable,
+    Dict,
+    Iterable,
+    List,
+    Mapping,
+    Callable,
+)
 
 # See API documentation for full information on the available functions:
 # https://ngrok.github.io/ngrok-python/
 
+def configure_runtime(
+    worker_threads: Optional[int] = None,
+    max_blocking_threads: Optional[int] = None,
+    thread_name: Optional[str] = None,
+    enable_io_uring: bool = False,
+) -> None: ...
 def connect(
     addr: Union[None, str, int] = None,
     listener: Optional[Listener] = None,
@@ -16,37 +33,137 @@ def forward(
     listener: Optional[Listener] = None,
     **options: object,
 ) -> Listener: ...
+def forward_from_config(
+    path: str,
+) -> Union[Awaitable[List[Listener]], List[Listener]]: ...
+def forward_many(
+    options: List[Mapping[str, Any]],
+) -> Union[Awaitable[List[Listener]], List[Listener]]: ...
 def getsockname(session: Optional[Session] = None) -> str: ...
+def install_signal_handlers(
+    signals: Optional[Iterable[int]] = None, drain_timeout: Optional[float] = None
+) -> None: ...
 def kill() -> None: ...
 def listen(
     server: Optional[Any] = None, listener: Optional[Listener] = None
 ) -> Listener: ...
 def log_level(level: str = "INFO") -> None: ...
 def pipe_name() -> str: ...
+def runtime_info() -> Dict[str, Any]: ...
+def serve_metrics(addr: str = "127.0.0.1:9464") -> str: ...
+def start_server(
+    client_connected_cb: Callable[[asyncio.StreamReader, asyncio.StreamWriter], Any],
+    listener: Listener,
+) -> Awaitable[Listener]: ...
 def werkzeug_develop(
     listener: Optional[Listener] = None,
 ) -> Union[Awaitable[Listener], Listener]: ...
 
-class Listener:
+class NgrokError(ValueError):
+    error_code: Optional[str]
+    server_message: Optional[str]
+    context: Optional[str]
+
+class SessionError(NgrokError): ...
+class ReconnectError(SessionError): ...
+class AuthError(NgrokError): ...
+class ListenerError(NgrokError): ...
+class ForwardError(NgrokError): ...
+class ConfigError(NgrokError):
+    line: Optional[int]
+    column: Optional[int]
+
+class Connection:
     def close(self) -> Awaitable[None]: ...
+    def drain(self) -> Awaitable[None]: ...
+    def proto(self) -> str: ...
+    def read(self, n: int = -1) -> Awaitable[bytes]: ...
+    def remote_addr(self) -> str: ...
+    def write(self, data: bytes) -> Awaitable[None]: ...
+
+class Listener:
+    def __aenter__(self) -> Awaitable[Listener]: ...
+    def __aexit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> Awaitable[bool]: ...
+    def __enter__(self) -> Listener: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+    def __aiter__(self) -> Listener: ...
+    def __anext__(self) -> Awaitable[Connection]: ...
+    def accept(self) -> Awaitable[Connection]: ...
+    def close(self, drain_timeout: Optional[float] = None) -> Awaitable[None]: ...
     def forward(self, addr: str) -> Awaitable[None]: ...
     def forwards_to(self) -> str: ...
     def id(self) -> str: ...
     def labels(self) -> Mapping[str, str]: ...
     def metadata(self) -> str: ...
+    def metrics(self) -> Dict[str, Optional[Union[int, float]]]: ...
     def proto(self) -> str: ...
+    def reconfigure(self, **options: Any) -> Awaitable[Listener]: ...
     def url(self) -> str: ...
 
 class Session:
+    def __aenter__(self) -> Awaitable[Session]: ...
+    def __aexit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> Awaitable[bool]: ...
+    def __enter__(self) -> Session: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+    @property
+    def state(self) -> str: ...
     def close(self) -> Awaitable[None]: ...
     def close_listener(self, id: str) -> Awaitable[None]: ...
+    def events(self) -> SessionEvents: ...
     def get_listeners(self) -> List[Listener]: ...
     def http_endpoint(self) -> HttpListenerBuilder: ...
     def labeled_listener(self) -> LabeledListenerBuilder: ...
     def tcp_endpoint(self) -> TcpListenerBuilder: ...
     def tls_endpoint(self) -> TlsListenerBuilder: ...
 
+class SyncListener:
+    def __init__(self, listener: Listener) -> None: ...
+    def __enter__(self) -> SyncListener: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+    @property
+    def listener(self) -> Listener: ...
+    def close(
+        self, drain_timeout: Optional[float] = None, timeout: Optional[float] = None
+    ) -> None: ...
+    def forwards_to(self) -> str: ...
+    def id(self) -> str: ...
+    def labels(self) -> Mapping[str, str]: ...
+    def metadata(self) -> str: ...
+    def metrics(self) -> Dict[str, Optional[Union[int, float]]]: ...
+    def proto(self) -> str: ...
+    def url(self) -> str: ...
+
+class SyncSession:
+    def __init__(self, session: Session) -> None: ...
+    def __enter__(self) -> SyncSession: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+    @property
+    def session(self) -> Session: ...
+    @property
+    def state(self) -> str: ...
+    def close(self, timeout: Optional[float] = None) -> None: ...
+    def close_listener(self, id: str, timeout: Optional[float] = None) -> None: ...
+    def get_listeners(self, timeout: Optional[float] = None) -> List[SyncListener]: ...
+    def http_endpoint(self) -> HttpListenerBuilder: ...
+    def labeled_listener(self) -> LabeledListenerBuilder: ...
+    def tcp_endpoint(self) -> TcpListenerBuilder: ...
+    def tls_endpoint(self) -> TlsListenerBuilder: ...
+
+class SessionEvent:
+    kind: str
+    error: Optional[str]
+    attempt: Optional[int]
+    latency: Optional[int]
+    version: Optional[str]
+    permit_major_version: Optional[bool]
+
+class SessionEvents:
+    def __aiter__(self) -> SessionEvents: ...
+    def __anext__(self) -> Awaitable[SessionEvent]: ...
+
 class SessionBuilder:
+    @staticmethod
+    def from_config(path: str) -> SessionBuilder: ...
     def authtoken(self, authtoken: str) -> SessionBuilder: ...
     def authtoken_from_env(self) -> SessionBuilder: ...
     def ca_cert(self, cert_bytes: bytearray) -> SessionBuilder: ...
@@ -54,33 +171,101 @@ class SessionBuilder:
         self, client_type: str, version: str, comments: Optional[str] = None
     ) -> SessionBuilder: ...
     def connect(self) -> Awaitable[Session]: ...
-    def handle_disconnection(self, handler: Callable[[str, str]]) -> SessionBuilder: ...
-    def handle_heartbeat(self, handler: Callable[[int]]) -> SessionBuilder: ...
-    def handle_restart_command(self, handler: Callable[[]]) -> SessionBuilder: ...
-    def handle_stop_command(self, handler: Callable[[]]) -> SessionBuilder: ...
+    def connect_sync(self, timeout: Optional[float] = None) -> SyncSession: ...
+    def handle_disconnection(
+        self, handler: Callable[[str, str], Union[None, Awaitable[None]]]
+    ) -> SessionBuilder: ...
+    def handle_heartbeat(
+        self, handler: Callable[[int], Union[None, Awaitable[None]]]
+    ) -> SessionBuilder: ...
+    def handle_restart_command(
+        self, handler: Callable[[], Union[None, Awaitable[None]]]
+    ) -> SessionBuilder: ...
+    def handle_stop_command(
+        self, handler: Callable[[], Union[None, Awaitable[None]]]
+    ) -> SessionBuilder: ...
     def handle_update_command(
-        self, handler: Callable[[str, str]]
+        self, handler: Callable[[str, bool], Union[None, Awaitable[None]]]
     ) -> SessionBuilder: ...
     def heartbeat_interval(self, heartbeat_interval: int) -> SessionBuilder: ...
     def heartbeat_tolerance(self, heartbeat_tolerance: int) -> SessionBuilder: ...
     def metadata(self, metadata: str) -> SessionBuilder: ...
+    def reconnect_policy(
+        self,
+        min_backoff: float = 0.5,
+        max_backoff: float = 30.0,
+        multiplier: float = 2.0,
+        jitter: float = 0.1,
+        max_attempts: Optional[int] = None,
+        give_up_after: Optional[float] = None,
+    ) -> SessionBuilder: ...
     def root_cas(self, root_cas: str) -> SessionBuilder: ...
     def server_addr(self, server_addr: str) -> SessionBuilder: ...
 
+class PolicyAction:
+    type: str
+    config: Optional[Dict[str, Any]]
+    def __init__(self, type: str, config: Optional[Mapping[str, Any]] = None) -> None: ...
+
+class PolicyRule:
+    name: Optional[str]
+    expressions: List[str]
+    actions: List[PolicyAction]
+    def __init__(
+        self,
+        actions: List[PolicyAction],
+        expressions: Optional[List[str]] = None,
+        name: Optional[str] = None,
+    ) -> None: ...
+
+class TrafficPolicy:
+    on_http_request: List[PolicyRule]
+    on_http_response: List[PolicyRule]
+    on_tcp_connect: List[PolicyRule]
+    def __init__(
+        self,
+        on_http_request: Optional[List[PolicyRule]] = None,
+        on_http_response: Optional[List[PolicyRule]] = None,
+        on_tcp_connect: Optional[List[PolicyRule]] = None,
+    ) -> None: ...
+    @staticmethod
+    def from_dict(policy: Mapping[str, Any]) -> TrafficPolicy: ...
+    @staticmethod
+    def from_json(json: str) -> TrafficPolicy: ...
+    @staticmethod
+    def from_yaml(yaml: str) -> TrafficPolicy: ...
+    @staticmethod
+    def load(path: str) -> TrafficPolicy: ...
+    def to_dict(self) -> Dict[str, Any]: ...
+    def to_json(self) -> str: ...
+    def to_yaml(self) -> str: ...
+
 class HttpListenerBuilder:
+    def __repr__(self) -> str: ...
     def allow_cidr(self, cidr: str) -> HttpListenerBuilder: ...
     def allow_user_agent(self, regex: str) -> HttpListenerBuilder: ...
     def app_protocol(self, app_protocol: str) -> HttpListenerBuilder: ...
     def basic_auth(self, username: str, password: str) -> HttpListenerBuilder: ...
     def circuit_breaker(self, circuit_breaker: float) -> HttpListenerBuilder: ...
     def compression(self) -> HttpListenerBuilder: ...
+    def config(self) -> Dict[str, Any]: ...
+    def copy(self) -> HttpListenerBuilder: ...
     def deny_cidr(self, cidr: str) -> HttpListenerBuilder: ...
     def deny_user_agent(self, regex: str) -> HttpListenerBuilder: ...
     def domain(self, domain: str) -> HttpListenerBuilder: ...
     def forwards_to(self, forwards_to: str) -> HttpListenerBuilder: ...
     def listen(self) -> Awaitable[Listener]: ...
-    def listen_and_forward(self, url: str) -> Awaitable[Listener]: ...
+    def listen_and_forward(
+        self, to_url: Union[str, List[str]], strategy: str = "failover"
+    ) -> Awaitable[Listener]: ...
+    def listen_and_forward_sync(
+        self,
+        to_url: Union[str, List[str]],
+        strategy: str = "failover",
+        timeout: Optional[float] = None,
+    ) -> SyncListener: ...
     def listen_and_serve(self, server: Any) -> Awaitable[Listener]: ...
+    def listen_sync(self, timeout: Optional[float] = None) -> SyncListener: ...
     def metadata(self, metadata: str) -> HttpListenerBuilder: ...
     def mutual_tlsca(self, mutual_tlsca: bytearray) -> HttpListenerBuilder: ...
     def oauth(
@@ -101,7 +286,7 @@ class HttpListenerBuilder:
         allow_domains: Optional[list[str]] = None,
         scopes: Optional[list[str]] = None,
     ) -> HttpListenerBuilder: ...
-    def policy(self, policy: str) -> HttpListenerBuilder: ...
+    def policy(self, policy: Union[str, TrafficPolicy]) -> HttpListenerBuilder: ...
     def pooling_enabled(self, pooling_enabled: bool) -> HttpListenerBuilder: ...
     def proxy_proto(self, proxy_proto: str) -> HttpListenerBuilder: ...
     def remove_request_header(self, name: str) -> HttpListenerBuilder: ...
@@ -109,54 +294,204 @@ class HttpListenerBuilder:
     def request_header(self, name: str, value: str) -> HttpListenerBuilder: ...
     def response_header(self, name: str, value: str) -> HttpListenerBuilder: ...
     def scheme(self, scheme: str) -> HttpListenerBuilder: ...
-    def traffic_policy(self, policy: str) -> HttpListenerBuilder: ...
+    def traffic_policy(self, policy: Union[str, TrafficPolicy]) -> HttpListenerBuilder: ...
     def verify_upstream_tls(self, verify_upstream_tls: bool) -> HttpListenerBuilder: ...
     def webhook_verification(
         self, provider: str, secret: str
     ) -> HttpListenerBuilder: ...
     def websocket_tcp_conversion(self) -> HttpListenerBuilder: ...
 
+class HttpOptions:
+    addr: Optional[str]
+    metadata: Optional[str]
+    allow_cidr: List[str]
+    deny_cidr: List[str]
+    proxy_proto: Optional[str]
+    forwards_to: Optional[str]
+    verify_upstream_tls: Optional[bool]
+    pooling_enabled: Optional[bool]
+    @property
+    def traffic_policy(self) -> Optional[str]: ...
+    @traffic_policy.setter
+    def traffic_policy(self, policy: Union[None, str, TrafficPolicy]) -> None: ...
+    domain: Optional[str]
+    schemes: List[str]
+    app_protocol: Optional[str]
+    mutual_tls_cas: List[str]
+    compression: Optional[bool]
+    websocket_tcp_converter: Optional[bool]
+    request_header_add: List[str]
+    response_header_add: List[str]
+    request_header_remove: List[str]
+    response_header_remove: List[str]
+    basic_auth: List[str]
+    allow_user_agent: List[str]
+    deny_user_agent: List[str]
+    circuit_breaker: Optional[float]
+    oauth_provider: Optional[str]
+    oauth_allow_emails: List[str]
+    oauth_allow_domains: List[str]
+    oauth_scopes: List[str]
+    oauth_client_id: Optional[str]
+    oauth_client_secret: Optional[str]
+    oidc_issuer_url: Optional[str]
+    oidc_client_id: Optional[str]
+    oidc_client_secret: Optional[str]
+    oidc_allow_emails: List[str]
+    oidc_allow_domains: List[str]
+    oidc_scopes: List[str]
+    verify_webhook_provider: Optional[str]
+    verify_webhook_secret: Optional[str]
+    def __init__(self, **options: Any) -> None: ...
+    @staticmethod
+    def from_dict(options: Mapping[str, Any]) -> HttpOptions: ...
+    @staticmethod
+    def from_json(json: str) -> HttpOptions: ...
+    def to_dict(self) -> Dict[str, Any]: ...
+    def to_json(self) -> str: ...
+    def apply(self, builder: HttpListenerBuilder) -> HttpListenerBuilder: ...
+
+class LabeledOptions:
+    addr: Optional[str]
+    metadata: Optional[str]
+    app_protocol: Optional[str]
+    verify_upstream_tls: Optional[bool]
+    labels: List[str]
+    def __init__(self, **options: Any) -> None: ...
+    @staticmethod
+    def from_dict(options: Mapping[str, Any]) -> LabeledOptions: ...
+    @staticmethod
+    def from_json(json: str) -> LabeledOptions: ...
+    def to_dict(self) -> Dict[str, Any]: ...
+    def to_json(self) -> str: ...
+    def apply(self, builder: LabeledListenerBuilder) -> LabeledListenerBuilder: ...
+
+class TcpOptions:
+    addr: Optional[str]
+    metadata: Optional[str]
+    allow_cidr: List[str]
+    deny_cidr: List[str]
+    proxy_proto: Optional[str]
+    forwards_to: Optional[str]
+    verify_upstream_tls: Optional[bool]
+    pooling_enabled: Optional[bool]
+    @property
+    def traffic_policy(self) -> Optional[str]: ...
+    @traffic_policy.setter
+    def traffic_policy(self, policy: Union[None, str, TrafficPolicy]) -> None: ...
+    remote_addr: Optional[str]
+    def __init__(self, **options: Any) -> None: ...
+    @staticmethod
+    def from_dict(options: Mapping[str, Any]) -> TcpOptions: ...
+    @staticmethod
+    def from_json(json: str) -> TcpOptions: ...
+    def to_dict(self) -> Dict[str, Any]: ...
+    def to_json(self) -> str: ...
+    def apply(self, builder: TcpListenerBuilder) -> TcpListenerBuilder: ...
+
+class TlsOptions:
+    addr: Optional[str]
+    metadata: Optional[str]
+    allow_cidr: List[str]
+    deny_cidr: List[str]
+    proxy_proto: Optional[str]
+    forwards_to: Optional[str]
+    verify_upstream_tls: Optional[bool]
+    pooling_enabled: Optional[bool]
+    @property
+    def traffic_policy(self) -> Optional[str]: ...
+    @traffic_policy.setter
+    def traffic_policy(self, policy: Union[None, str, TrafficPolicy]) -> None: ...
+    domain: Optional[str]
+    mutual_tls_cas: List[str]
+    crt: Optional[str]
+    key: Optional[str]
+    def __init__(self, **options: Any) -> None: ...
+    @staticmethod
+    def from_dict(options: Mapping[str, Any]) -> TlsOptions: ...
+    @staticmethod
+    def from_json(json: str) -> TlsOptions: ...
+    def to_dict(self) -> Dict[str, Any]: ...
+    def to_json(self) -> str: ...
+    def apply(self, builder: TlsListenerBuilder) -> TlsListenerBuilder: ...
+
 class LabeledListenerBuilder:
+    def __repr__(self) -> str: ...
     def app_protocol(self, app_protocol: str) -> LabeledListenerBuilder: ...
+    def config(self) -> Dict[str, Any]: ...
+    def copy(self) -> LabeledListenerBuilder: ...
     def label(self, label: str, value: str) -> LabeledListenerBuilder: ...
     def listen(self) -> Awaitable[Listener]: ...
-    def listen_and_forward(self, url: str) -> Awaitable[Listener]: ...
+    def listen_and_forward(
+        self, to_url: Union[str, List[str]], strategy: str = "failover"
+    ) -> Awaitable[Listener]: ...
+    def listen_and_forward_sync(
+        self,
+        to_url: Union[str, List[str]],
+        strategy: str = "failover",
+        timeout: Optional[float] = None,
+    ) -> SyncListener: ...
     def listen_and_serve(self, server: Any) -> Awaitable[Listener]: ...
+    def listen_sync(self, timeout: Optional[float] = None) -> SyncListener: ...
     def metadata(self, metadata: str) -> LabeledListenerBuilder: ...
     def verify_upstream_tls(
         self, verify_upstream_tls: bool
     ) -> LabeledListenerBuilder: ...
 
 class TcpListenerBuilder:
+    def __repr__(self) -> str: ...
     def allow_cidr(self, cidr: str) -> TcpListenerBuilder: ...
+    def config(self) -> Dict[str, Any]: ...
+    def copy(self) -> TcpListenerBuilder: ...
     def deny_cidr(self, cidr: str) -> TcpListenerBuilder: ...
     def forwards_to(self, forwards_to: str) -> TcpListenerBuilder: ...
     def listen(self) -> Awaitable[Listener]: ...
-    def listen_and_forward(self, url: str) -> Awaitable[Listener]: ...
+    def listen_and_forward(
+        self, to_url: Union[str, List[str]], strategy: str = "failover"
+    ) -> Awaitable[Listener]: ...
+    def listen_and_forward_sync(
+        self,
+        to_url: Union[str, List[str]],
+        strategy: str = "failover",
+        timeout: Optional[float] = None,
+    ) -> SyncListener: ...
     def listen_and_serve(self, server: Any) -> Awaitable[Listener]: ...
+    def listen_sync(self, timeout: Optional[float] = None) -> SyncListener: ...
     def metadata(self, metadata: str) -> TcpListenerBuilder: ...
-    def policy(self, policy: str) -> TcpListenerBuilder: ...
+    def policy(self, policy: Union[str, TrafficPolicy]) -> TcpListenerBuilder: ...
     def pooling_enabled(self, pooling_enabled: bool) -> TcpListenerBuilder: ...
     def proxy_proto(self, proxy_proto: str) -> TcpListenerBuilder: ...
     def remote_addr(self, remote_addr: str) -> TcpListenerBuilder: ...
-    def traffic_policy(self, policy: str) -> TcpListenerBuilder: ...
+    def traffic_policy(self, policy: Union[str, TrafficPolicy]) -> TcpListenerBuilder: ...
     def verify_upstream_tls(self, verify_upstream_tls: bool) -> TcpListenerBuilder: ...
 
 class TlsListenerBuilder:
+    def __repr__(self) -> str: ...
     def allow_cidr(self, cidr: str) -> TlsListenerBuilder: ...
+    def config(self) -> Dict[str, Any]: ...
+    def copy(self) -> TlsListenerBuilder: ...
     def deny_cidr(self, cidr: str) -> TlsListenerBuilder: ...
     def domain(self, domain: str) -> TlsListenerBuilder: ...
     def forwards_to(self, forwards_to: str) -> TlsListenerBuilder: ...
     def listen(self) -> Awaitable[Listener]: ...
-    def listen_and_forward(self, url: str) -> Awaitable[Listener]: ...
+    def listen_and_forward(
+        self, to_url: Union[str, List[str]], strategy: str = "failover"
+    ) -> Awaitable[Listener]: ...
+    def listen_and_forward_sync(
+        self,
+        to_url: Union[str, List[str]],
+        strategy: str = "failover",
+        timeout: Optional[float] = None,
+    ) -> SyncListener: ...
     def listen_and_serve(self, server: Any) -> Awaitable[Listener]: ...
+    def listen_sync(self, timeout: Optional[float] = None) -> SyncListener: ...
     def metadata(self, metadata: str) -> TlsListenerBuilder: ...
     def mutual_tlsca(self, mutual_tlsca: bytearray) -> TlsListenerBuilder: ...
-    def policy(self, policy: str) -> TlsListenerBuilder: ...
+    def policy(self, policy: Union[str, TrafficPolicy]) -> TlsListenerBuilder: ...
     def pooling_enabled(self, pooling_enabled: bool) -> TlsListenerBuilder: ...
     def proxy_proto(self, proxy_proto: str) -> TlsListenerBuilder: ...
     def termination(
         self, cert_pem: bytearray, key_pem: bytearray
     ) -> TlsListenerBuilder: ...
-    def traffic_policy(self, policy: str) -> TlsListenerBuilder: ...
+    def traffic_policy(self, policy: Union[str, TrafficPolicy]) -> TlsListenerBuilder: ...
     def verify_upstream_tls(self, verify_upstream_tls: bool) -> TlsListenerBuilder: ...
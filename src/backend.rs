@@ -0,0 +1,145 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use parking_lot::Mutex as SyncMutex;
+use pyo3::{
+    exceptions::PyRuntimeError,
+    intern,
+    pyclass,
+    pymethods,
+    types::{
+        PyAnyMethods,
+        PyCFunction,
+        PyModule,
+    },
+    Bound,
+    IntoPy,
+    Py,
+    PyAny,
+    PyResult,
+    Python,
+};
+use tracing::debug;
+
+type PyFuture = Pin<Box<dyn Future<Output = PyResult<Py<PyAny>>> + Send>>;
+
+/// Convert a future into an awaitable for the async library running in this thread. Under
+/// asyncio, including AnyIO's asyncio backend, this is an asyncio future. Under Trio,
+/// including AnyIO's trio backend, the future is run on the tokio runtime and wakes the
+/// awaiting task through its Trio token.
+pub(crate) fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<Py<PyAny>> + Send + 'static,
+{
+    if pyo3_async_runtimes::get_running_loop(py).is_err() && in_trio(py) {
+        let fut: PyFuture = Box::pin(async move {
+            let value = fut.await?;
+            Ok(Python::with_gil(|py| value.into_py(py)))
+        });
+        let trio_future = TrioFuture {
+            fut: SyncMutex::new(Some(fut)),
+        };
+        return Ok(Bound::new(py, trio_future)?.into_any());
+    }
+    pyo3_async_runtimes::tokio::future_into_py(py, fut)
+}
+
+/// Whether an asyncio loop or a Trio run is active in this thread, so an awaitable
+/// returned from `future_into_py` can be awaited.
+pub(crate) fn running(py: Python) -> bool {
+    pyo3_async_runtimes::get_running_loop(py).is_ok() || in_trio(py)
+}
+
+/// Whether this thread is inside trio.run. Trio is only checked for once something has
+/// imported it.
+fn in_trio(py: Python) -> bool {
+    let imported = PyModule::import_bound(py, intern!(py, "sys"))
+        .and_then(|sys| sys.getattr(intern!(py, "modules"))?.contains("trio"))
+        .unwrap_or(false);
+    imported
+        && PyModule::import_bound(py, intern!(py, "trio.lowlevel"))
+            .and_then(|lowlevel| lowlevel.call_method0(intern!(py, "current_trio_token")))
+            .is_ok()
+}
+
+/// An awaitable for Trio, which starts its future on the tokio runtime when awaited and
+/// suspends the awaiting task until the future is done. Cancelling the task aborts the
+/// future.
+#[pyclass]
+struct TrioFuture {
+    fut: SyncMutex<Option<PyFuture>>,
+}
+
+#[pymethods]
+impl TrioFuture {
+    fn __await__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let fut = self
+            .fut
+            .lock()
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("cannot reuse already awaited future"))?;
+        let lowlevel = PyModule::import_bound(py, intern!(py, "trio.lowlevel"))?;
+        let task = lowlevel.call_method0(intern!(py, "current_task"))?.unbind();
+        let token = lowlevel
+            .call_method0(intern!(py, "current_trio_token"))?
+            .unbind();
+
+        // both wake and abort run in the trio thread, so only one of them acts
+        let finished = Arc::new(AtomicBool::new(false));
+        let result = Arc::new(SyncMutex::new(None));
+        let wake = {
+            let finished = finished.clone();
+            let result = result.clone();
+            PyCFunction::new_closure_bound(py, None, None, move |args, _kwargs| {
+                if finished.swap(true, Ordering::SeqCst) {
+                    return Ok(());
+                }
+                let py = args.py();
+                let outcome = PyModule::import_bound(py, intern!(py, "outcome"))?;
+                let outcome = match result.lock().take() {
+                    Some(Ok(value)) => outcome.call_method1(intern!(py, "Value"), (value,))?,
+                    Some(Err(e)) => outcome.call_method1(intern!(py, "Error"), (e,))?,
+                    None => return Ok(()),
+                };
+                PyModule::import_bound(py, intern!(py, "trio.lowlevel"))?
+                    .call_method1(intern!(py, "reschedule"), (&task, outcome))?;
+                Ok::<_, pyo3::PyErr>(())
+            })?
+            .unbind()
+        };
+
+        let handle = pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            let res = fut.await;
+            *result.lock() = Some(res);
+            Python::with_gil(|py| {
+                // fails once the trio run has finished, with nothing left to wake
+                if let Err(e) = token.call_method1(py, intern!(py, "run_sync_soon"), (wake,)) {
+                    debug!("Unable to wake trio task: {e:?}");
+                }
+            });
+        });
+
+        let abort_succeeded = lowlevel
+            .getattr(intern!(py, "Abort"))?
+            .getattr(intern!(py, "SUCCEEDED"))?
+            .unbind();
+        let abort = PyCFunction::new_closure_bound(py, None, None, move |args, _kwargs| {
+            finished.store(true, Ordering::SeqCst);
+            handle.abort();
+            abort_succeeded.clone_ref(args.py())
+        })?;
+        lowlevel
+            .call_method1(intern!(py, "wait_task_rescheduled"), (abort,))?
+            .call_method0(intern!(py, "__await__"))
+    }
+}
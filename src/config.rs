@@ -0,0 +1,492 @@
+use std::fs;
+
+use pyo3::{
+    pyfunction,
+    types::{
+        PyByteArray,
+        PyDict,
+        PyDictMethods,
+        PyList,
+        PyListMethods,
+    },
+    Bound,
+    IntoPy,
+    Py,
+    PyAny,
+    PyObject,
+    PyResult,
+    Python,
+};
+use serde_yaml::{
+    Mapping,
+    Value,
+};
+use tracing::{
+    info,
+    warn,
+};
+use url::Url;
+
+use crate::{
+    backend,
+    connect::{
+        configure_session,
+        listen_with_options,
+    },
+    listener::{
+        remove_session_listeners,
+        Listener,
+    },
+    py_err,
+    session::SessionBuilder,
+    wrapper,
+    ConfigError,
+};
+
+/// A parsed ngrok agent configuration file, as options for :meth:`forward`.
+pub(crate) struct AgentConfig {
+    path: String,
+    session: Py<PyDict>,
+    tunnels: Vec<(String, Py<PyDict>)>,
+}
+
+impl AgentConfig {
+    /// Read and translate the ngrok agent configuration file at the given path.
+    pub(crate) fn load(py: Python, path: &str) -> PyResult<Self> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            py_err::<ConfigError>(format!("failed to read ngrok config {path}: {e}"))
+        })?;
+        let doc: Value = serde_yaml::from_str(&text).map_err(|e| {
+            py_err::<ConfigError>(format!("failed to parse ngrok config {path}: {e}"))
+        })?;
+        let root = match doc {
+            Value::Mapping(root) => root,
+            Value::Null => Mapping::new(),
+            _ => {
+                return Err(py_err::<ConfigError>(format!(
+                    "ngrok config {path} must be a mapping"
+                )))
+            }
+        };
+
+        let version = match root.get("version") {
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => {
+                return Err(py_err::<ConfigError>(format!(
+                    "invalid version in ngrok config {path}: {other:?}"
+                )))
+            }
+            None if root.contains_key("agent") || root.contains_key("endpoints") => "3".into(),
+            None => "2".into(),
+        };
+
+        let (session, tunnels) = match version.as_str() {
+            "2" => (
+                session_options(py, &root, "server_addr", "root_cas")?,
+                v2_tunnels(py, &root)?,
+            ),
+            "3" => {
+                let agent = match root.get("agent") {
+                    Some(agent) => mapping(agent, "agent")?.clone(),
+                    None => Mapping::new(),
+                };
+                (
+                    session_options(py, &agent, "connect_url", "connect_cas")?,
+                    v3_endpoints(py, &root)?,
+                )
+            }
+            _ => {
+                return Err(py_err::<ConfigError>(format!(
+                    "unsupported version {version:?} in ngrok config {path}, expected 2 or 3"
+                )))
+            }
+        };
+
+        Ok(AgentConfig {
+            path: path.to_string(),
+            session: session.unbind(),
+            tunnels,
+        })
+    }
+
+    /// A session builder with the configured authtoken, server address and root CAs.
+    pub(crate) fn session_builder(&self) -> PyResult<SessionBuilder> {
+        configure_session(&self.session)
+    }
+
+    /// Connect a session and start a forwarding listener for each configured tunnel.
+    /// If any listener fails to start, the session is closed and the error is raised.
+    pub(crate) async fn forward(self) -> PyResult<Vec<Listener>> {
+        let session = self.session_builder()?.async_connect().await?;
+        let mut listeners = vec![];
+        for (name, options) in self.tunnels {
+            match listen_with_options(&session, options).await {
+                Ok(listener) => {
+                    info!("Started tunnel {name:?} from {}", self.path);
+                    listeners.push(listener);
+                }
+                Err(e) => {
+                    warn!("Failed to start tunnel {name:?} from {}: {e}", self.path);
+                    let _ = session.async_close().await;
+                    remove_session_listeners(&session.id()).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(listeners)
+    }
+}
+
+/// Start a listener for each tunnel (version 2) or endpoint (version 3) in an ngrok agent
+/// configuration file, on a new session using the file's ``authtoken``, ``server_addr``
+/// and ``root_cas``. If no authtoken is configured, the NGROK_AUTHTOKEN environment
+/// variable is used.
+///
+/// :param str path: The path of the ngrok agent configuration file, e.g. "ngrok.yml".
+/// :return: The list of forwarding Listeners, or a task to await for them if an event loop is running.
+#[pyfunction]
+pub fn forward_from_config(py: Python, path: String) -> PyResult<Py<PyAny>> {
+    let config = AgentConfig::load(py, &path)?;
+    // move to async, handling if there is an async loop running or not
+    wrapper::bridge(py, config.forward())
+}
+
+#[pyfunction]
+pub fn async_forward_from_config(py: Python, path: String) -> PyResult<Bound<'_, PyAny>> {
+    let config = AgentConfig::load(py, &path)?;
+    backend::future_into_py(py, async move { config.forward().await })
+}
+
+fn session_options<'py>(
+    py: Python<'py>,
+    section: &Mapping,
+    server_addr_key: &str,
+    root_cas_key: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = PyDict::new_bound(py);
+    match section.get("authtoken") {
+        Some(token) => options.set_item("authtoken", string(token, "authtoken")?)?,
+        None => options.set_item("authtoken_from_env", true)?,
+    }
+    // version 3 renamed these, accept either spelling
+    for (key, option) in [
+        (server_addr_key, "server_addr"),
+        ("server_addr", "server_addr"),
+        (root_cas_key, "root_cas"),
+        ("root_cas", "root_cas"),
+        ("metadata", "session_metadata"),
+    ] {
+        if let Some(value) = section.get(key) {
+            options.set_item(option, string(value, key)?)?;
+        }
+    }
+    Ok(options)
+}
+
+fn v2_tunnels(py: Python, root: &Mapping) -> PyResult<Vec<(String, Py<PyDict>)>> {
+    let Some(tunnels) = root.get("tunnels") else {
+        return Ok(vec![]);
+    };
+    let mut out = vec![];
+    for (name, tunnel) in mapping(tunnels, "tunnels")? {
+        let name = string(name, "tunnels")?;
+        let options = v2_tunnel(py, &name, mapping(tunnel, &name)?)?;
+        out.push((name, options.unbind()));
+    }
+    Ok(out)
+}
+
+/// Translate a version 2 tunnel definition into :meth:`forward` options.
+fn v2_tunnel<'py>(py: Python<'py>, name: &str, tunnel: &Mapping) -> PyResult<Bound<'py, PyDict>> {
+    let options = PyDict::new_bound(py);
+    for (key, value) in tunnel {
+        let key = string(key, name)?;
+        let ctx = format!("{name}.{key}");
+        match key.as_str() {
+            "addr" => options.set_item("addr", addr(value, &ctx)?)?,
+            "proto" | "metadata" | "remote_addr" | "app_protocol" | "domain" => {
+                options.set_item(key.as_str(), string(value, &ctx)?)?
+            }
+            "hostname" => options.set_item("domain", string(value, &ctx)?)?,
+            "proxy_proto" => options.set_item("proxy_proto", string(value, &ctx)?)?,
+            "schemes" | "basic_auth" => options.set_item(key.as_str(), strings(value, &ctx)?)?,
+            "compression"
+            | "websocket_tcp_converter"
+            | "verify_upstream_tls"
+            | "pooling_enabled" => options.set_item(key.as_str(), boolean(value, &ctx)?)?,
+            "circuit_breaker" => options.set_item("circuit_breaker", float(value, &ctx)?)?,
+            "crt" | "key" => options.set_item(key.as_str(), file(py, value, &ctx)?)?,
+            "mutual_tls_cas" => {
+                let cas = PyList::empty_bound(py);
+                for path in strings(value, &ctx)? {
+                    cas.append(file(py, &Value::String(path), &ctx)?)?;
+                }
+                options.set_item("mutual_tls_cas", cas)?;
+            }
+            "ip_restriction" => nested(
+                &options,
+                value,
+                &ctx,
+                &[("allow_cidrs", "allow_cidr"), ("deny_cidrs", "deny_cidr")],
+            )?,
+            "user_agent_filter" => nested(
+                &options,
+                value,
+                &ctx,
+                &[("allow", "allow_user_agent"), ("deny", "deny_user_agent")],
+            )?,
+            "request_header" => nested(
+                &options,
+                value,
+                &ctx,
+                &[
+                    ("add", "request_header_add"),
+                    ("remove", "request_header_remove"),
+                ],
+            )?,
+            "response_header" => nested(
+                &options,
+                value,
+                &ctx,
+                &[
+                    ("add", "response_header_add"),
+                    ("remove", "response_header_remove"),
+                ],
+            )?,
+            "oauth" => nested(
+                &options,
+                value,
+                &ctx,
+                &[
+                    ("provider", "oauth_provider"),
+                    ("allow_emails", "oauth_allow_emails"),
+                    ("allow_domains", "oauth_allow_domains"),
+                    ("scopes", "oauth_scopes"),
+                    ("client_id", "oauth_client_id"),
+                    ("client_secret", "oauth_client_secret"),
+                ],
+            )?,
+            "oidc" => nested(
+                &options,
+                value,
+                &ctx,
+                &[
+                    ("issuer_url", "oidc_issuer_url"),
+                    ("client_id", "oidc_client_id"),
+                    ("client_secret", "oidc_client_secret"),
+                    ("allow_emails", "oidc_allow_emails"),
+                    ("allow_domains", "oidc_allow_domains"),
+                    ("scopes", "oidc_scopes"),
+                ],
+            )?,
+            "verify_webhook" => nested(
+                &options,
+                value,
+                &ctx,
+                &[
+                    ("provider", "verify_webhook_provider"),
+                    ("secret", "verify_webhook_secret"),
+                ],
+            )?,
+            "labels" => {
+                // the agent writes labels as "name=value"
+                let labels: Vec<String> = strings(value, &ctx)?
+                    .into_iter()
+                    .map(|label| label.replacen('=', ":", 1))
+                    .collect();
+                options.set_item("labels", labels)?;
+            }
+            "traffic_policy" | "policy" => {
+                options.set_item("traffic_policy", policy(value, &ctx)?)?
+            }
+            _ => warn!("Ignoring unsupported option {ctx:?} in ngrok config"),
+        }
+    }
+    if !options.contains("proto")? {
+        if options.contains("labels")? {
+            options.set_item("proto", "labeled")?;
+        } else {
+            return Err(py_err::<ConfigError>(format!(
+                "tunnel {name:?} is missing proto"
+            )));
+        }
+    }
+    Ok(options)
+}
+
+fn v3_endpoints(py: Python, root: &Mapping) -> PyResult<Vec<(String, Py<PyDict>)>> {
+    let Some(endpoints) = root.get("endpoints") else {
+        return Ok(vec![]);
+    };
+    let Value::Sequence(endpoints) = endpoints else {
+        return Err(py_err::<ConfigError>("endpoints must be a list"));
+    };
+    let mut out = vec![];
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let endpoint = mapping(endpoint, "endpoints")?;
+        let name = match endpoint.get("name") {
+            Some(name) => string(name, "endpoints.name")?,
+            None => format!("endpoint-{}", i + 1),
+        };
+        let options = v3_endpoint(py, &name, endpoint)?;
+        out.push((name, options.unbind()));
+    }
+    Ok(out)
+}
+
+/// Translate a version 3 endpoint definition into :meth:`forward` options.
+fn v3_endpoint<'py>(
+    py: Python<'py>,
+    name: &str,
+    endpoint: &Mapping,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = PyDict::new_bound(py);
+    options.set_item("proto", "http")?;
+    for (key, value) in endpoint {
+        let key = string(key, name)?;
+        let ctx = format!("{name}.{key}");
+        match key.as_str() {
+            "name" | "description" => {}
+            "url" => {
+                let url = string(value, &ctx)?;
+                let parsed = Url::parse(&url)
+                    .map_err(|e| py_err::<ConfigError>(format!("invalid url for {ctx}: {e}")))?;
+                let host = parsed.host_str().filter(|h| !h.is_empty());
+                match parsed.scheme() {
+                    scheme @ ("http" | "https") => {
+                        options.set_item("schemes", vec![scheme.to_uppercase()])?;
+                        if let Some(host) = host {
+                            options.set_item("domain", host)?;
+                        }
+                    }
+                    "tcp" => {
+                        options.set_item("proto", "tcp")?;
+                        if let (Some(host), Some(port)) = (host, parsed.port()) {
+                            options.set_item("remote_addr", format!("{host}:{port}"))?;
+                        }
+                    }
+                    "tls" => {
+                        options.set_item("proto", "tls")?;
+                        if let Some(host) = host {
+                            options.set_item("domain", host)?;
+                        }
+                    }
+                    scheme => {
+                        return Err(py_err::<ConfigError>(format!(
+                            "unsupported scheme {scheme:?} for {ctx}"
+                        )))
+                    }
+                }
+            }
+            "upstream" => {
+                for (key, value) in mapping(value, &ctx)? {
+                    let key = string(key, &ctx)?;
+                    let ctx = format!("{ctx}.{key}");
+                    match key.as_str() {
+                        "url" => options.set_item("addr", addr(value, &ctx)?)?,
+                        "protocol" => options.set_item("app_protocol", string(value, &ctx)?)?,
+                        "proxy_protocol_version" => {
+                            options.set_item("proxy_proto", string(value, &ctx)?)?
+                        }
+                        _ => warn!("Ignoring unsupported option {ctx:?} in ngrok config"),
+                    }
+                }
+            }
+            "metadata" => options.set_item("metadata", string(value, &ctx)?)?,
+            "pooling_enabled" => options.set_item("pooling_enabled", boolean(value, &ctx)?)?,
+            "traffic_policy" => options.set_item("traffic_policy", policy(value, &ctx)?)?,
+            _ => warn!("Ignoring unsupported option {ctx:?} in ngrok config"),
+        }
+    }
+    if !options.contains("addr")? {
+        return Err(py_err::<ConfigError>(format!(
+            "endpoint {name:?} is missing upstream.url"
+        )));
+    }
+    Ok(options)
+}
+
+/// Copy the values of a nested section into options, renaming their keys.
+fn nested(
+    options: &Bound<PyDict>,
+    value: &Value,
+    ctx: &str,
+    keys: &[(&str, &str)],
+) -> PyResult<()> {
+    for (key, value) in mapping(value, ctx)? {
+        let key = string(key, ctx)?;
+        let ctx = format!("{ctx}.{key}");
+        match keys.iter().find(|(from, _)| *from == key) {
+            Some((_, to)) => match value {
+                Value::Sequence(_) => options.set_item(*to, strings(value, &ctx)?)?,
+                _ => options.set_item(*to, string(value, &ctx)?)?,
+            },
+            None => warn!("Ignoring unsupported option {ctx:?} in ngrok config"),
+        }
+    }
+    Ok(())
+}
+
+fn mapping<'a>(value: &'a Value, ctx: &str) -> PyResult<&'a Mapping> {
+    value
+        .as_mapping()
+        .ok_or_else(|| py_err::<ConfigError>(format!("{ctx} must be a mapping")))
+}
+
+fn string(value: &Value, ctx: &str) -> PyResult<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        _ => Err(py_err::<ConfigError>(format!("{ctx} must be a string"))),
+    }
+}
+
+fn strings(value: &Value, ctx: &str) -> PyResult<Vec<String>> {
+    match value {
+        Value::Sequence(values) => values.iter().map(|v| string(v, ctx)).collect(),
+        _ => Ok(vec![string(value, ctx)?]),
+    }
+}
+
+fn boolean(value: &Value, ctx: &str) -> PyResult<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| py_err::<ConfigError>(format!("{ctx} must be true or false")))
+}
+
+fn float(value: &Value, ctx: &str) -> PyResult<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| py_err::<ConfigError>(format!("{ctx} must be a number")))
+}
+
+/// The address to forward to, where a bare port number means a port on localhost.
+fn addr(value: &Value, ctx: &str) -> PyResult<String> {
+    let addr = string(value, ctx)?;
+    if !addr.is_empty() && addr.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(format!("localhost:{addr}"));
+    }
+    Ok(addr)
+}
+
+/// A traffic policy given either inline as a mapping or as a JSON or YAML string.
+fn policy(value: &Value, ctx: &str) -> PyResult<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Mapping(_) => serde_json::to_string(value)
+            .map_err(|e| py_err::<ConfigError>(format!("invalid {ctx}: {e}"))),
+        _ => Err(py_err::<ConfigError>(format!(
+            "{ctx} must be a mapping or a string"
+        ))),
+    }
+}
+
+/// The contents of the file at the path given by the value.
+fn file(py: Python, value: &Value, ctx: &str) -> PyResult<PyObject> {
+    let path = string(value, ctx)?;
+    let bytes = fs::read(&path)
+        .map_err(|e| py_err::<ConfigError>(format!("failed to read {ctx} file {path}: {e}")))?;
+    Ok(PyByteArray::new_bound(py, &bytes).into_py(py))
+}
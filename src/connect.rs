@@ -1,33 +1,47 @@
+use std::{
+    mem,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use futures::future;
 use lazy_static::lazy_static;
 use log::{
     debug,
     info,
 };
+use ngrok::Session as NgrokSession;
 use pyo3::{
+    pyclass::boolean_struct::False,
     pyfunction,
     types::{
+        PyAnyMethods,
         PyBool,
         PyByteArray,
+        PyBytes,
         PyDict,
         PyFloat,
         PyInt,
         PyList,
         PyString,
+        PyTuple,
     },
     Bound,
     IntoPy,
     Py,
     PyAny,
     PyCell,
-    PyDowncastError,
+    PyClass,
     PyErr,
     PyObject,
+    PyRefMut,
     PyResult,
     Python,
 };
 use tokio::sync::Mutex;
 
 use crate::{
+    backend,
     listener::{
         self,
         Listener,
@@ -39,14 +53,35 @@ use crate::{
         TcpListenerBuilder,
         TlsListenerBuilder,
     },
+    policy::PolicyConfig,
     py_err,
     session::{
         Session,
         SessionBuilder,
     },
+    upstream::UpstreamPool,
     wrapper,
+    ConfigError,
+    ListenerError,
 };
 
+/// Lets the plumb macros treat fallible and infallible builder setters the same way.
+trait Plumbed {
+    fn plumbed(self) -> PyResult<()>;
+}
+
+impl<T: PyClass<Frozen = False>> Plumbed for PyRefMut<'_, T> {
+    fn plumbed(self) -> PyResult<()> {
+        Ok(())
+    }
+}
+
+impl<T: PyClass<Frozen = False>> Plumbed for PyResult<PyRefMut<'_, T>> {
+    fn plumbed(self) -> PyResult<()> {
+        self.map(|_| ())
+    }
+}
+
 lazy_static! {
     // Save a user-facing Session to use for connect use cases
     pub(crate) static ref SESSION: Mutex<Option<Session>> = Mutex::new(None);
@@ -59,7 +94,8 @@ macro_rules! plumb {
     };
     ($builder:tt, $self:tt, $config:tt, $name:tt, $config_name:tt) => {
         if let Some(v) = $config.get_item(stringify!($config_name))? {
-            $builder::$name($self.borrow_mut(), get_string(v)?);
+            $builder::$name($self.borrow_mut(), get_string(stringify!($config_name), v)?)
+                .plumbed()?;
         }
     };
 }
@@ -71,7 +107,7 @@ macro_rules! plumb_bool {
     };
     ($builder:tt, $self:tt, $config:tt, $name:tt, $config_name:tt) => {
         if let Some(v) = $config.get_item(stringify!($config_name))? {
-            if get_bool(v)? {
+            if get_bool(stringify!($config_name), v)? {
                 $builder::$name($self.borrow_mut());
             }
         }
@@ -81,7 +117,7 @@ macro_rules! plumb_bool {
 macro_rules! plumb_bool_2arg {
     ($builder:tt, $self:tt, $config:tt, $name:tt) => {
         if let Some(v) = $config.get_item(stringify!($name))? {
-            $builder::$name($self.borrow_mut(), get_bool(v)?);
+            $builder::$name($self.borrow_mut(), get_bool(stringify!($name), v)?);
         }
     };
 }
@@ -94,22 +130,32 @@ macro_rules! plumb_vec {
     ($builder:tt, $self:tt, $config:tt, $name:tt, $config_name:tt) => {
         if let Some(v) = $config.get_item(stringify!($config_name))? {
             for val in get_list(v)? {
-                $builder::$name($self.borrow_mut(), get_string(val)?);
+                $builder::$name(
+                    $self.borrow_mut(),
+                    get_string(stringify!($config_name), val)?,
+                )
+                .plumbed()?;
             }
         }
     };
     ($builder:tt, $self:tt, $config:tt, $name:tt, $config_name:tt, vecu8) => {
         if let Some(v) = $config.get_item(stringify!($config_name))? {
             for val in get_list(v)? {
-                $builder::$name($self.borrow_mut(), get_byte_array(val)?);
+                $builder::$name($self.borrow_mut(), get_pem(stringify!($config_name), val)?);
             }
         }
     };
     ($builder:tt, $self:tt, $config:tt, $name:tt, $config_name:tt, $split:tt) => {
         if let Some(v) = $config.get_item(stringify!($config_name))? {
             for val in get_list(v)? {
-                let s = get_string(val)?;
-                let (a, b) = s.split_once($split).expect("split of value failed: ${val}");
+                let key = stringify!($config_name);
+                let s = get_string(key, val)?;
+                let (a, b) = s.split_once($split).ok_or_else(|| {
+                    py_err::<ConfigError>(format!(
+                        "option {key:?} must be in the form \"a{}b\", got {s:?}",
+                        $split
+                    ))
+                })?;
                 $builder::$name($self.borrow_mut(), a.to_string(), b.to_string());
             }
         }
@@ -125,38 +171,113 @@ macro_rules! config_common {
         plumb!($builder, $self, $config, proxy_proto);
         plumb!($builder, $self, $config, forwards_to);
         plumb_bool_2arg!($builder, $self, $config, verify_upstream_tls);
-        plumb!($builder, $self, $config, traffic_policy);
+        plumb_bool_2arg!($builder, $self, $config, pooling_enabled);
         // policy is currently an alias of traffic_policy, it will eventually be removed.
-        plumb!($builder, $self, $config, traffic_policy, policy);
+        for key in ["traffic_policy", "policy"] {
+            if let Some(v) = $config.get_item(key)? {
+                $builder::traffic_policy($self.borrow_mut(), get_policy(key, v)?);
+            }
+        }
     };
 }
 
-fn get_string(v: &PyAny) -> Result<String, PyErr> {
-    v.downcast::<PyString>()?.extract::<String>()
+/// An error for an option value which can't be used, naming the option.
+fn option_err(key: &str, expected: &str, v: &PyAny) -> PyErr {
+    let type_name = v
+        .get_type()
+        .name()
+        .map(|n| n.to_string())
+        .unwrap_or_default();
+    py_err::<ConfigError>(format!(
+        "option {key:?} must be {expected}, got {type_name}"
+    ))
+}
+
+pub(crate) fn get_string(key: &str, v: &PyAny) -> PyResult<String> {
+    v.downcast::<PyString>()
+        .map_err(|_| option_err(key, "a string", v))?
+        .extract::<String>()
 }
 
-fn get_bool(v: &PyAny) -> Result<bool, PyErr> {
-    v.downcast::<PyBool>()?.extract::<bool>()
+/// Booleans, also accepting the strings of env-derived config such as "true" or "0".
+pub(crate) fn get_bool(key: &str, v: &PyAny) -> PyResult<bool> {
+    if let Ok(b) = v.downcast::<PyBool>() {
+        return Ok(b.is_true());
+    }
+    if let Ok(s) = v.downcast::<PyString>() {
+        match s.to_str()?.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => return Ok(true),
+            "false" | "0" | "no" | "off" => return Ok(false),
+            _ => {}
+        }
+    }
+    Err(option_err(key, "a bool, or \"true\" or \"false\"", v))
 }
 
-fn get_list(v: &PyAny) -> Result<Vec<&PyAny>, PyErr> {
-    if v.is_instance(v.py().get_type::<PyList>())? {
-        return v.downcast::<PyList>()?.extract::<Vec<&PyAny>>();
+/// Floats, also accepting ints and numeric strings.
+pub(crate) fn get_float(key: &str, v: &PyAny) -> PyResult<f64> {
+    // bools are ints to Python, but never a sensible number here
+    if !v.is_instance_of::<PyBool>()
+        && (v.is_instance_of::<PyFloat>() || v.is_instance_of::<PyInt>())
+    {
+        return v.extract::<f64>();
+    }
+    if let Ok(s) = v.downcast::<PyString>() {
+        if let Ok(f) = s.to_str()?.trim().parse::<f64>() {
+            return Ok(f);
+        }
+    }
+    Err(option_err(key, "a number", v))
+}
+
+/// Traffic policy, as text or a TrafficPolicy.
+pub(crate) fn get_policy(key: &str, v: &PyAny) -> PyResult<PolicyConfig> {
+    v.extract::<PolicyConfig>()
+        .map_err(|_| option_err(key, "a string or TrafficPolicy", v))
+}
+
+pub(crate) fn get_list(v: &PyAny) -> PyResult<Vec<&PyAny>> {
+    if v.is_instance_of::<PyList>() || v.is_instance_of::<PyTuple>() {
+        return v.extract::<Vec<&PyAny>>();
     }
     // turn scalars into lists
     Ok(vec![v])
 }
 
-fn get_str_list(v: Option<&PyAny>) -> Result<Option<Vec<String>>, PyErr> {
+fn get_str_list(cfg: &PyDict, key: &str) -> PyResult<Option<Vec<String>>> {
     // vectorize PyAny's, then convert them to Strings
-    v.map(get_list)
+    cfg.get_item(key)?
+        .map(get_list)
         .transpose()?
-        .map(|v| v.iter().map(|v| get_string(v)).collect())
+        .map(|v| v.iter().map(|v| get_string(key, v)).collect())
         .transpose()
 }
 
-fn get_byte_array(v: &PyAny) -> Result<&PyByteArray, PyDowncastError> {
-    v.downcast::<PyByteArray>()
+fn get_opt_string(cfg: &PyDict, key: &str) -> PyResult<Option<String>> {
+    cfg.get_item(key)?.map(|v| get_string(key, v)).transpose()
+}
+
+/// Certificate material, from a bytearray, bytes, PEM text, or the path of a PEM file.
+pub(crate) fn get_pem<'py>(key: &str, v: &'py PyAny) -> PyResult<&'py PyByteArray> {
+    if let Ok(b) = v.downcast::<PyByteArray>() {
+        return Ok(b);
+    }
+    let pem = if let Ok(b) = v.downcast::<PyBytes>() {
+        b.as_bytes().to_vec()
+    } else if v.is_instance_of::<PyString>() && get_string(key, v)?.contains("-----BEGIN") {
+        get_string(key, v)?.into_bytes()
+    } else if let Ok(path) = v.extract::<PathBuf>() {
+        // a str or os.PathLike which isn't PEM text is a file to read
+        std::fs::read(&path).map_err(|e| {
+            py_err::<ConfigError>(format!(
+                "option {key:?} could not read {}: {e}",
+                path.display()
+            ))
+        })?
+    } else {
+        return Err(option_err(key, "bytes, PEM text or a path", v));
+    };
+    Ok(PyByteArray::new(v.py(), &pem))
 }
 
 /// Alias for :meth:`forward`
@@ -175,6 +296,9 @@ pub fn connect(
 
 /// Establish ngrok ingress, returning an Listener object.
 ///
+/// Unknown options, and options which don't apply to the chosen proto, raise a
+/// :class:`ConfigError` unless ``strict=False`` is passed, in which case they are ignored.
+///
 /// :param int, str or None addr: The address to forward traffic to, this can be an integer port, or a host:port string, or url, e.g. "80", "localhost:8080", "https://192.168.1.100:8443", or "unix:/path/to/unix.sock"
 /// :param str or None proto: The protocol type of the Listener, one of "http", "tcp", "tls", "labeled"
 /// :param options: A dict of options to pass to the Listener.
@@ -187,7 +311,40 @@ pub fn forward(
     proto: Option<String>,
     options: Option<&PyDict>,
 ) -> PyResult<Py<PyAny>> {
-    let kwargs: &PyDict = options.unwrap_or(PyDict::new(py));
+    let kwargs = prepare_options(py, addr, proto, options.unwrap_or(PyDict::new(py)))?;
+
+    // move to async, handling if there is an async loop running or not
+    wrapper::bridge(py, do_connect(kwargs.into()))
+}
+
+/// Establish several ngrok ingresses at once on the shared session, returning a list of
+/// Listener objects. The listeners are created concurrently, and if any of them fails to
+/// start, the others are closed and a single :class:`ListenerError` is raised. Its
+/// ``errors`` attribute holds the individual errors.
+///
+/// :param options: A list of dicts with the same keys as the options of :meth:`forward`, including "addr" and "proto". The session options are taken from the first, and later dicts may only repeat them.
+/// :return: A list of Listener objects.
+#[pyfunction]
+pub fn forward_many(py: Python, options: Vec<&PyDict>) -> PyResult<Py<PyAny>> {
+    let mut prepared = vec![];
+    for opts in options {
+        // work on a copy so the caller's dicts are left as they were
+        let kwargs = opts.copy()?;
+        let addr = kwargs.get_item("addr")?;
+        prepared.push(prepare_options(py, addr, None, kwargs)?.into());
+    }
+
+    // move to async, handling if there is an async loop running or not
+    wrapper::bridge(py, do_forward_many(prepared))
+}
+
+/// Normalize the address and options for a listener as given to :meth:`forward`.
+pub(crate) fn prepare_options<'a>(
+    py: Python,
+    addr: Option<&PyAny>,
+    proto: Option<String>,
+    kwargs: &'a PyDict,
+) -> PyResult<&'a PyDict> {
     // decode address string
     let mut addr_str = format!("{TCP_PREFIX}localhost:80");
     if let Some(a) = addr {
@@ -223,31 +380,203 @@ pub fn forward(
         if let Some(v) = kwargs.get_item(k)? {
             if v.is_none() {
                 kwargs.del_item(k)?;
-            } else if get_string(k)?.contains('.') {
+            } else if k.extract::<String>()?.contains('.') {
                 // handle cases like "oauth.provider" -> "oauth_provider"
                 kwargs.del_item(k)?;
-                kwargs.set_item(get_string(k)?.replace('.', "_"), v)?;
+                kwargs.set_item(k.extract::<String>()?.replace('.', "_"), v)?;
             }
         }
     }
 
-    // move to async, handling if there is an async loop running or not
-    wrapper::loop_wrap(
-        py,
-        Some(kwargs.into()),
-        "    return await ngrok.async_connect(input)",
-    )
+    let strict = match kwargs.get_item("strict")? {
+        Some(v) => get_bool("strict", v)?,
+        None => true,
+    };
+    kwargs.del_item("strict").ok();
+    if strict {
+        check_options(kwargs)?;
+    }
+
+    Ok(kwargs)
+}
+
+/// Options which configure the session, or which are read for every proto.
+const SESSION_OPTIONS: &[&str] = &[
+    "addr",
+    "authtoken",
+    "authtoken_from_env",
+    "force_new_session",
+    "proto",
+    "root_cas",
+    "server_addr",
+    "session_ca_cert",
+    "session_metadata",
+];
+
+/// Options read by `config_common!` for the http, tcp and tls protos.
+const COMMON_OPTIONS: &[&str] = &[
+    "allow_cidr",
+    "deny_cidr",
+    "forwards_to",
+    "metadata",
+    "policy",
+    "pooling_enabled",
+    "proxy_proto",
+    "traffic_policy",
+    "verify_upstream_tls",
+];
+
+const HTTP_OPTIONS: &[&str] = &[
+    "allow_user_agent",
+    "app_protocol",
+    "basic_auth",
+    "circuit_breaker",
+    "compression",
+    "deny_user_agent",
+    "domain",
+    "hostname",
+    "mutual_tls_cas",
+    "oauth_allow_domains",
+    "oauth_allow_emails",
+    "oauth_client_id",
+    "oauth_client_secret",
+    "oauth_provider",
+    "oauth_scopes",
+    "oidc_allow_domains",
+    "oidc_allow_emails",
+    "oidc_client_id",
+    "oidc_client_secret",
+    "oidc_issuer_url",
+    "oidc_scopes",
+    "request_header_add",
+    "request_header_remove",
+    "response_header_add",
+    "response_header_remove",
+    "schemes",
+    "verify_webhook_provider",
+    "verify_webhook_secret",
+    "websocket_tcp_converter",
+];
+
+const TCP_OPTIONS: &[&str] = &["remote_addr"];
+
+const TLS_OPTIONS: &[&str] = &["crt", "domain", "hostname", "key", "mutual_tls_cas"];
+
+const LABELED_OPTIONS: &[&str] = &["app_protocol", "labels", "metadata", "verify_upstream_tls"];
+
+/// The listener options understood for a proto, or None if the proto is unknown.
+fn proto_options(proto: &str) -> Option<Vec<&'static str>> {
+    let specific: &[&[&str]] = match proto {
+        "http" => &[COMMON_OPTIONS, HTTP_OPTIONS],
+        "tcp" => &[COMMON_OPTIONS, TCP_OPTIONS],
+        "tls" => &[COMMON_OPTIONS, TLS_OPTIONS],
+        "labeled" => &[LABELED_OPTIONS],
+        _ => return None,
+    };
+    Some(specific.concat())
+}
+
+/// Raise a ConfigError for any option which is unknown, or which the proto doesn't use.
+fn check_options(kwargs: &PyDict) -> PyResult<()> {
+    let proto = match kwargs.get_item("proto")? {
+        Some(p) => get_string("proto", p)?,
+        None => "http".to_string(),
+    };
+    // an unknown proto is reported when the listener is created
+    let Some(applicable) = proto_options(&proto) else {
+        return Ok(());
+    };
+    let known: Vec<&str> = [
+        SESSION_OPTIONS,
+        COMMON_OPTIONS,
+        HTTP_OPTIONS,
+        TCP_OPTIONS,
+        TLS_OPTIONS,
+        LABELED_OPTIONS,
+    ]
+    .concat();
+
+    let mut keys = kwargs
+        .keys()
+        .iter()
+        .map(|k| k.extract::<String>())
+        .collect::<PyResult<Vec<_>>>()?;
+    keys.sort();
+    let mut problems = vec![];
+    for key in keys {
+        let key = key.as_str();
+        if SESSION_OPTIONS.contains(&key) || applicable.contains(&key) {
+            continue;
+        }
+        if known.contains(&key) {
+            problems.push(format!("option {key:?} does not apply to proto {proto:?}"));
+            continue;
+        }
+        // prefer suggesting an option which applies to this proto
+        let candidates = SESSION_OPTIONS.iter().chain(applicable.iter());
+        match did_you_mean(key, candidates.copied()) {
+            Some(s) => problems.push(format!("unknown option {key:?}, did you mean {s:?}?")),
+            None => problems.push(format!("unknown option {key:?}")),
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(py_err::<ConfigError>(format!(
+        "{} (pass strict=False to ignore)",
+        problems.join("; ")
+    )))
+}
+
+/// The closest candidate to a misspelled option, if any is close enough to be a typo.
+pub(crate) fn did_you_mean<'a>(
+    key: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let key = key.to_lowercase().replace('-', "_");
+    let max_distance = (key.len() / 3).max(2);
+    candidates
+        .map(|c| (edit_distance(&key, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance, counting a swap of adjacent characters as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }
 
 #[pyfunction]
 pub fn async_connect(py: Python, config: Py<PyDict>) -> PyResult<Bound<'_, PyAny>> {
-    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+    backend::future_into_py(py, async move {
         let result = do_connect(config).await;
         Python::with_gil(|py| result.map(|obj| obj.into_py(py)))
     })
 }
 
-fn configure_session(options: &Py<PyDict>) -> Result<SessionBuilder, PyErr> {
+pub(crate) fn configure_session(options: &Py<PyDict>) -> Result<SessionBuilder, PyErr> {
     Python::with_gil(|py: Python| {
         let s_builder = PyCell::new(py, SessionBuilder::new())?;
         let cfg = options.as_ref(py);
@@ -263,169 +592,354 @@ fn configure_session(options: &Py<PyDict>) -> Result<SessionBuilder, PyErr> {
 }
 
 async fn do_connect(options: Py<PyDict>) -> PyResult<PyObject> {
+    // Using a singleton session for connect use cases
+    let mut opt = SESSION.lock().await;
+    let session = shared_session(&mut opt, &options).await?;
+
+    let listener = listen_with_options(session, options).await?;
+    Ok(Python::with_gil(|py| listener.into_py(py)))
+}
+
+#[pyfunction]
+pub fn async_forward_many(py: Python, options: Vec<Py<PyDict>>) -> PyResult<Bound<'_, PyAny>> {
+    backend::future_into_py(py, async move { do_forward_many(options).await })
+}
+
+async fn do_forward_many(options: Vec<Py<PyDict>>) -> PyResult<Vec<Listener>> {
+    let Some(first) = options.first() else {
+        return Ok(vec![]);
+    };
+    check_session_options(&options)?;
+    let session = {
+        let mut opt = SESSION.lock().await;
+        shared_session(&mut opt, first).await?.clone()
+    };
+
+    let total = options.len();
+    let results = future::join_all(
+        options
+            .into_iter()
+            .map(|options| listen_with_options(&session, options)),
+    )
+    .await;
+
+    let mut listeners = vec![];
+    let mut failures = vec![];
+    for (i, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(listener) => listeners.push(listener),
+            Err(e) => failures.push((i, e)),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(listeners);
+    }
+
+    // don't leave the listeners which did start behind
+    for listener in listeners {
+        if let Err(e) = listener.async_close().await {
+            debug!("Error closing listener {}: {e:?}", listener.id());
+        }
+    }
+    Err(aggregate_error(failures, total))
+}
+
+/// Reject session options in later dicts of a batch which differ from the first's, since
+/// all of the listeners share the session the first dict configures.
+fn check_session_options(options: &[Py<PyDict>]) -> PyResult<()> {
+    Python::with_gil(|py| {
+        let first = options[0].as_ref(py);
+        for (i, opts) in options.iter().enumerate().skip(1) {
+            let opts = opts.as_ref(py);
+            for key in SESSION_OPTIONS {
+                if *key == "addr" || *key == "proto" {
+                    continue;
+                }
+                let differs = match (first.get_item(key)?, opts.get_item(key)?) {
+                    (_, None) => false,
+                    (Some(a), Some(b)) => !a.eq(b)?,
+                    (None, Some(_)) => true,
+                };
+                if differs {
+                    return Err(py_err::<ConfigError>(format!(
+                        "\"{key}\" in options [{i}] differs from options [0], session options are taken from the first"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Get the shared session, connecting it with the given options if there is none yet or
+/// a new one is forced.
+async fn shared_session<'a>(
+    opt: &'a mut Option<Session>,
+    options: &Py<PyDict>,
+) -> PyResult<&'a Session> {
     let force_new_session = Python::with_gil(|py| -> PyResult<bool> {
         if let Some(v) = options.as_ref(py).get_item("force_new_session")? {
-            return get_bool(v);
+            return get_bool("force_new_session", v);
         }
         Ok(false)
     })?;
 
-    // Using a singleton session for connect use cases
-    let mut opt = SESSION.lock().await;
     if opt.is_none() || force_new_session {
-        opt.replace(configure_session(&options)?.async_connect().await?);
+        opt.replace(configure_session(options)?.async_connect().await?);
     }
-    let session = opt.as_ref().unwrap();
+    Ok(opt.as_ref().unwrap())
+}
+
+/// A single error for the listeners of a batch which failed to start, by their index.
+fn aggregate_error(failures: Vec<(usize, PyErr)>, total: usize) -> PyErr {
+    Python::with_gil(|py| {
+        let details: Vec<String> = failures.iter().map(|(i, e)| format!("[{i}] {e}")).collect();
+        let err = py_err::<ListenerError>(format!(
+            "failed to start {} of {total} listeners: {}",
+            failures.len(),
+            details.join("; ")
+        ));
+        let errors = PyList::new_bound(py, failures.iter().map(|(_, e)| e.value_bound(py)));
+        if let Err(e) = err.value_bound(py).setattr("errors", errors) {
+            debug!("Error setting errors on exception: {e:?}");
+        }
+        err.set_cause(py, failures.into_iter().next().map(|(_, e)| e));
+        err
+    })
+}
 
+/// Create a listener of the configured proto, forwarding to the configured addr.
+pub(crate) async fn listen_with_options(
+    session: &Session,
+    options: Py<PyDict>,
+) -> PyResult<Listener> {
     // decode address
     let addr = Python::with_gil(|py| -> PyResult<String> {
         // decode address string
         get_string(
+            "addr",
             options
                 .as_ref(py)
                 .get_item("addr")?
-                .ok_or_else(|| py_err("addr not set"))?,
+                .ok_or_else(|| py_err::<ConfigError>("addr not set"))?,
         )
     })?;
 
-    // decode proto
-    let proto = Python::with_gil(|py| -> PyResult<String> {
-        Ok(match options.as_ref(py).get_item("proto")? {
-            Some(p) => get_string(p)?,
-            None => "http".to_string(),
-        })
-    })?;
-
     // create Listener
-    let listener = match proto.as_str() {
+    let proto = get_proto(&options)?;
+    match proto.as_str() {
         "http" => http_endpoint(session, addr, options).await,
         "tcp" => tcp_endpoint(session, addr, options).await,
         "tls" => tls_endpoint(session, addr, options).await,
         "labeled" => labeled_listener(session, addr, options).await,
-        _ => Err(py_err(format!("unhandled protocol {proto:?}"))),
-    }?;
-    Ok(Python::with_gil(|py| listener.into_py(py)))
+        _ => Err(py_err::<ConfigError>(format!(
+            "unhandled protocol {proto:?}"
+        ))),
+    }
+}
+
+/// Create a listener of the configured proto, forwarding to an existing pool of upstreams.
+pub(crate) async fn forward_with_options(
+    session: &NgrokSession,
+    options: Py<PyDict>,
+    pool: Arc<UpstreamPool>,
+    forwards_to: Option<String>,
+) -> PyResult<Listener> {
+    let proto = get_proto(&options)?;
+    match proto.as_str() {
+        "http" => {
+            http_builder(session, &options)?
+                .async_forward(pool, forwards_to)
+                .await
+        }
+        "tcp" => {
+            tcp_builder(session, &options)?
+                .async_forward(pool, forwards_to)
+                .await
+        }
+        "tls" => {
+            tls_builder(session, &options)?
+                .async_forward(pool, forwards_to)
+                .await
+        }
+        "labeled" => {
+            labeled_builder(session, &options)?
+                .async_forward(pool, forwards_to)
+                .await
+        }
+        _ => Err(py_err::<ConfigError>(format!(
+            "unhandled protocol {proto:?}"
+        ))),
+    }
+}
+
+/// The proto of a :meth:`forward` dict, defaulting to http.
+fn get_proto(options: &Py<PyDict>) -> PyResult<String> {
+    Python::with_gil(|py| {
+        Ok(match options.as_ref(py).get_item("proto")? {
+            Some(p) => get_string("proto", p)?,
+            None => "http".to_string(),
+        })
+    })
 }
 
 /// HTTP Listener creation and forwarding
 async fn http_endpoint(session: &Session, addr: String, options: Py<PyDict>) -> PyResult<Listener> {
-    let bld = Python::with_gil(|py: Python| {
-        let bld = PyCell::new(py, session.http_endpoint())?;
-        let cfg = options.as_ref(py);
-        type B = HttpListenerBuilder;
-        config_common!(B, bld, cfg);
-        plumb_vec!(B, bld, cfg, scheme, schemes);
-        plumb!(B, bld, cfg, domain, hostname); // synonym for domain
-        plumb!(B, bld, cfg, domain);
-        plumb!(B, bld, cfg, app_protocol);
-        plumb_vec!(B, bld, cfg, mutual_tlsca, mutual_tls_cas, vecu8);
-        plumb_bool!(B, bld, cfg, compression);
-        plumb_bool!(
-            B,
-            bld,
-            cfg,
-            websocket_tcp_conversion,
-            websocket_tcp_converter
+    let bld = http_builder(&session.raw_session(), &options)?;
+    let listener = bld.async_listen().await?;
+    listener.spawn_forward(addr)?;
+    Ok(listener)
+}
+
+/// An http listener builder configured from the options of a :meth:`forward` dict.
+fn http_builder(session: &NgrokSession, options: &Py<PyDict>) -> PyResult<HttpListenerBuilder> {
+    let fresh = || HttpListenerBuilder::new(session.clone(), session.http_endpoint());
+    Python::with_gil(|py: Python| {
+        let bld = Bound::new(py, fresh())?;
+        configure_http(&bld, options.as_ref(py))?;
+        Ok(mem::replace(&mut *bld.borrow_mut(), fresh()))
+    })
+}
+
+/// Apply the http listener options of a :meth:`forward` dict to a builder.
+pub(crate) fn configure_http(bld: &Bound<'_, HttpListenerBuilder>, cfg: &PyDict) -> PyResult<()> {
+    type B = HttpListenerBuilder;
+    config_common!(B, bld, cfg);
+    plumb_vec!(B, bld, cfg, scheme, schemes);
+    plumb!(B, bld, cfg, domain, hostname); // synonym for domain
+    plumb!(B, bld, cfg, domain);
+    plumb!(B, bld, cfg, app_protocol);
+    plumb_vec!(B, bld, cfg, mutual_tlsca, mutual_tls_cas, vecu8);
+    plumb_bool!(B, bld, cfg, compression);
+    plumb_bool!(
+        B,
+        bld,
+        cfg,
+        websocket_tcp_conversion,
+        websocket_tcp_converter
+    );
+    plumb_vec!(B, bld, cfg, request_header, request_header_add, ":");
+    plumb_vec!(B, bld, cfg, response_header, response_header_add, ":");
+    plumb_vec!(B, bld, cfg, remove_request_header, request_header_remove);
+    plumb_vec!(B, bld, cfg, remove_response_header, response_header_remove);
+    plumb_vec!(B, bld, cfg, basic_auth, basic_auth, ":");
+    plumb_vec!(B, bld, cfg, allow_user_agent, allow_user_agent);
+    plumb_vec!(B, bld, cfg, deny_user_agent, deny_user_agent);
+    // circuit breaker
+    if let Some(cb) = cfg.get_item("circuit_breaker")? {
+        let cb64 = get_float("circuit_breaker", cb)?;
+        HttpListenerBuilder::circuit_breaker(bld.borrow_mut(), cb64);
+    }
+    // oauth
+    if let Some(provider) = cfg.get_item("oauth_provider")? {
+        HttpListenerBuilder::oauth(
+            bld.borrow_mut(),
+            get_string("oauth_provider", provider)?,
+            get_str_list(cfg, "oauth_allow_emails")?,
+            get_str_list(cfg, "oauth_allow_domains")?,
+            get_str_list(cfg, "oauth_scopes")?,
+            get_opt_string(cfg, "oauth_client_id")?,
+            get_opt_string(cfg, "oauth_client_secret")?,
         );
-        plumb_vec!(B, bld, cfg, request_header, request_header_add, ":");
-        plumb_vec!(B, bld, cfg, response_header, response_header_add, ":");
-        plumb_vec!(B, bld, cfg, remove_request_header, request_header_remove);
-        plumb_vec!(B, bld, cfg, remove_response_header, response_header_remove);
-        plumb_vec!(B, bld, cfg, basic_auth, basic_auth, ":");
-        plumb_vec!(B, bld, cfg, allow_user_agent, allow_user_agent);
-        plumb_vec!(B, bld, cfg, deny_user_agent, deny_user_agent);
-        // circuit breaker
-        if let Some(cb) = cfg.get_item("circuit_breaker")? {
-            let cb64 = cb.downcast::<PyFloat>()?.extract::<f64>()?;
-            HttpListenerBuilder::circuit_breaker(bld.borrow_mut(), cb64);
-        }
-        // oauth
-        if let Some(provider) = cfg.get_item("oauth_provider")? {
-            HttpListenerBuilder::oauth(
-                bld.borrow_mut(),
-                get_string(provider)?,
-                get_str_list(cfg.get_item("oauth_allow_emails")?)?,
-                get_str_list(cfg.get_item("oauth_allow_domains")?)?,
-                get_str_list(cfg.get_item("oauth_scopes")?)?,
-                cfg.get_item("oauth_client_id")?
-                    .map(|v| get_string(v))
-                    .transpose()?,
-                cfg.get_item("oauth_client_secret")?
-                    .map(|v| get_string(v))
-                    .transpose()?,
-            );
-        }
-        // oidc
-        if let Some(issuer_url) = cfg.get_item("oidc_issuer_url")? {
-            let client_id = cfg.get_item("oidc_client_id")?
-                .ok_or_else(|| py_err("Missing client id for oidc. oidc_client_id must be set if oidc_issuer_url is set"))?;
-            let client_secret = cfg.get_item("oidc_client_secret")?
-                .ok_or_else(|| py_err("Missing client secret for oidc. oidc_client_secret must be set if oidc_issuer_url is set"))?;
-            HttpListenerBuilder::oidc(
+    }
+    // oidc
+    if let Some(issuer_url) = cfg.get_item("oidc_issuer_url")? {
+        let client_id = cfg.get_item("oidc_client_id")?.ok_or_else(|| {
+            py_err::<ConfigError>(
+                "Missing client id for oidc. oidc_client_id must be set if oidc_issuer_url is set",
+            )
+        })?;
+        let client_secret = cfg.get_item("oidc_client_secret")?
+            .ok_or_else(|| py_err::<ConfigError>("Missing client secret for oidc. oidc_client_secret must be set if oidc_issuer_url is set"))?;
+        HttpListenerBuilder::oidc(
+            bld.borrow_mut(),
+            get_string("oidc_issuer_url", issuer_url)?,
+            get_string("oidc_client_id", client_id)?,
+            get_string("oidc_client_secret", client_secret)?,
+            get_str_list(cfg, "oidc_allow_emails")?,
+            get_str_list(cfg, "oidc_allow_domains")?,
+            get_str_list(cfg, "oidc_scopes")?,
+        );
+    }
+    // webhook verification
+    if let Some(provider) = cfg.get_item("verify_webhook_provider")? {
+        if let Some(secret) = cfg.get_item("verify_webhook_secret")? {
+            HttpListenerBuilder::webhook_verification(
                 bld.borrow_mut(),
-                get_string(issuer_url)?,
-                get_string(client_id)?,
-                get_string(client_secret)?,
-                get_str_list(cfg.get_item("oidc_allow_emails")?)?,
-                get_str_list(cfg.get_item("oidc_allow_domains")?)?,
-                get_str_list(cfg.get_item("oidc_scopes")?)?,
+                get_string("verify_webhook_provider", provider)?,
+                get_string("verify_webhook_secret", secret)?,
             );
+        } else {
+            return Err(py_err::<ConfigError>("Missing key for tls termination"));
         }
-        // webhook verification
-        if let Some(provider) = cfg.get_item("verify_webhook_provider")? {
-            if let Some(secret) = cfg.get_item("verify_webhook_secret")? {
-                HttpListenerBuilder::webhook_verification(
-                    bld.borrow_mut(),
-                    get_string(provider)?,
-                    get_string(secret)?,
-                );
-            } else {
-                return Err(py_err("Missing key for tls termination"));
-            }
-        }
-        Ok::<_, PyErr>(bld.replace(session.http_endpoint()))
-    })?;
-    spawn_forward(bld.async_listen().await?, addr).await
+    }
+    Ok(())
 }
 
 /// TCP Listener creation and forwarding
 async fn tcp_endpoint(session: &Session, addr: String, options: Py<PyDict>) -> PyResult<Listener> {
-    let bld = Python::with_gil(|py: Python| {
-        let bld = PyCell::new(py, session.tcp_endpoint())?;
-        let cfg = options.as_ref(py);
-        type B = TcpListenerBuilder;
-        config_common!(B, bld, cfg);
-        plumb!(B, bld, cfg, remote_addr);
-        Ok::<_, PyErr>(bld.replace(session.tcp_endpoint()))
-    })?;
-    spawn_forward(bld.async_listen().await?, addr).await
+    let bld = tcp_builder(&session.raw_session(), &options)?;
+    let listener = bld.async_listen().await?;
+    listener.spawn_forward(addr)?;
+    Ok(listener)
+}
+
+/// A tcp listener builder configured from the options of a :meth:`forward` dict.
+fn tcp_builder(session: &NgrokSession, options: &Py<PyDict>) -> PyResult<TcpListenerBuilder> {
+    let fresh = || TcpListenerBuilder::new(session.clone(), session.tcp_endpoint());
+    Python::with_gil(|py: Python| {
+        let bld = Bound::new(py, fresh())?;
+        configure_tcp(&bld, options.as_ref(py))?;
+        Ok(mem::replace(&mut *bld.borrow_mut(), fresh()))
+    })
+}
+
+/// Apply the tcp listener options of a :meth:`forward` dict to a builder.
+pub(crate) fn configure_tcp(bld: &Bound<'_, TcpListenerBuilder>, cfg: &PyDict) -> PyResult<()> {
+    type B = TcpListenerBuilder;
+    config_common!(B, bld, cfg);
+    plumb!(B, bld, cfg, remote_addr);
+    Ok(())
 }
 
 /// TLS Listener creation and forwarding
 async fn tls_endpoint(session: &Session, addr: String, options: Py<PyDict>) -> PyResult<Listener> {
-    let bld = Python::with_gil(|py: Python| {
-        let bld = PyCell::new(py, session.tls_endpoint())?;
-        let cfg = options.as_ref(py);
-        type B = TlsListenerBuilder;
-        config_common!(B, bld, cfg);
-        plumb!(B, bld, cfg, domain, hostname); // synonym for domain
-        plumb!(B, bld, cfg, domain);
-        plumb_vec!(B, bld, cfg, mutual_tlsca, mutual_tls_cas, vecu8);
-        // tls termination
-        if let Some(crt) = cfg.get_item("crt")? {
-            if let Some(key) = cfg.get_item("key")? {
-                TlsListenerBuilder::termination(
-                    bld.borrow_mut(),
-                    get_byte_array(crt)?,
-                    get_byte_array(key)?,
-                );
-            } else {
-                return Err(py_err("Missing key for tls termination"));
-            }
+    let bld = tls_builder(&session.raw_session(), &options)?;
+    let listener = bld.async_listen().await?;
+    listener.spawn_forward(addr)?;
+    Ok(listener)
+}
+
+/// A tls listener builder configured from the options of a :meth:`forward` dict.
+fn tls_builder(session: &NgrokSession, options: &Py<PyDict>) -> PyResult<TlsListenerBuilder> {
+    let fresh = || TlsListenerBuilder::new(session.clone(), session.tls_endpoint());
+    Python::with_gil(|py: Python| {
+        let bld = Bound::new(py, fresh())?;
+        configure_tls(&bld, options.as_ref(py))?;
+        Ok(mem::replace(&mut *bld.borrow_mut(), fresh()))
+    })
+}
+
+/// Apply the tls listener options of a :meth:`forward` dict to a builder.
+pub(crate) fn configure_tls(bld: &Bound<'_, TlsListenerBuilder>, cfg: &PyDict) -> PyResult<()> {
+    type B = TlsListenerBuilder;
+    config_common!(B, bld, cfg);
+    plumb!(B, bld, cfg, domain, hostname); // synonym for domain
+    plumb!(B, bld, cfg, domain);
+    plumb_vec!(B, bld, cfg, mutual_tlsca, mutual_tls_cas, vecu8);
+    // tls termination
+    if let Some(crt) = cfg.get_item("crt")? {
+        if let Some(key) = cfg.get_item("key")? {
+            TlsListenerBuilder::termination(
+                bld.borrow_mut(),
+                get_pem("crt", crt)?,
+                get_pem("key", key)?,
+            );
+        } else {
+            return Err(py_err::<ConfigError>("Missing key for tls termination"));
         }
-        Ok::<_, PyErr>(bld.replace(session.tls_endpoint()))
-    })?;
-    spawn_forward(bld.async_listen().await?, addr).await
+    }
+    Ok(())
 }
 
 /// Labeled Listener creation and forwarding
@@ -434,25 +948,36 @@ async fn labeled_listener(
     addr: String,
     options: Py<PyDict>,
 ) -> PyResult<Listener> {
-    let bld = Python::with_gil(|py: Python| {
-        let bld = PyCell::new(py, session.labeled_listener())?;
-        let cfg = options.as_ref(py);
-        type B = LabeledListenerBuilder;
-        plumb!(B, bld, cfg, metadata);
-        plumb!(B, bld, cfg, app_protocol);
-        plumb_bool_2arg!(B, bld, cfg, verify_upstream_tls);
-        plumb_vec!(B, bld, cfg, label, labels, ":");
-        Ok::<_, PyErr>(bld.replace(session.labeled_listener()))
-    })?;
-    spawn_forward(bld.async_listen().await?, addr).await
+    let bld = labeled_builder(&session.raw_session(), &options)?;
+    let listener = bld.async_listen().await?;
+    listener.spawn_forward(addr)?;
+    Ok(listener)
 }
 
-/// Background the Listener forwarding
-async fn spawn_forward(listener: Listener, addr: String) -> PyResult<Listener> {
-    let id = listener.id();
-    // move forwarding to another task
-    tokio::spawn(async move { listener::forward(&id, addr).await.map(|_| ()) });
-    Ok(listener)
+/// A labeled listener builder configured from the options of a :meth:`forward` dict.
+fn labeled_builder(
+    session: &NgrokSession,
+    options: &Py<PyDict>,
+) -> PyResult<LabeledListenerBuilder> {
+    let fresh = || LabeledListenerBuilder::new(session.clone(), session.labeled_tunnel());
+    Python::with_gil(|py: Python| {
+        let bld = Bound::new(py, fresh())?;
+        configure_labeled(&bld, options.as_ref(py))?;
+        Ok(mem::replace(&mut *bld.borrow_mut(), fresh()))
+    })
+}
+
+/// Apply the labeled listener options of a :meth:`forward` dict to a builder.
+pub(crate) fn configure_labeled(
+    bld: &Bound<'_, LabeledListenerBuilder>,
+    cfg: &PyDict,
+) -> PyResult<()> {
+    type B = LabeledListenerBuilder;
+    plumb!(B, bld, cfg, metadata);
+    plumb!(B, bld, cfg, app_protocol);
+    plumb_bool_2arg!(B, bld, cfg, verify_upstream_tls);
+    plumb_vec!(B, bld, cfg, label, labels, ":");
+    Ok(())
 }
 
 /// Shut down all listeners and sessions.
@@ -467,22 +992,21 @@ pub fn kill(py: Python) -> PyResult<Py<PyAny>> {
 #[pyfunction]
 #[pyo3(text_signature = "(url=None)")]
 pub fn disconnect(py: Python, url: Option<Py<PyString>>) -> PyResult<Py<PyAny>> {
+    let url = url.map(|u| u.to_string());
     // move to async, handling if there is an async loop running or not
-    wrapper::loop_wrap(
-        py,
-        url.map(|u| u.into()),
-        "    return await ngrok.async_disconnect(input)",
-    )
+    wrapper::bridge(py, do_disconnect(url))
 }
 
 #[pyfunction]
 pub fn async_disconnect(py: Python, url: Option<String>) -> PyResult<Bound<'_, PyAny>> {
+    backend::future_into_py(py, do_disconnect(url))
+}
+
+async fn do_disconnect(url: Option<String>) -> PyResult<()> {
     debug!("Disconnecting. url: {url:?}");
-    pyo3_async_runtimes::tokio::future_into_py(py, async move {
-        listener::close_url(url.clone()).await?;
-        if url.is_none() {
-            SESSION.lock().await.take();
-        }
-        Ok(())
-    })
+    listener::close_url(url.clone()).await?;
+    if url.is_none() {
+        SESSION.lock().await.take();
+    }
+    Ok(())
 }
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use ngrok::{
+    prelude::*,
+    EdgeConn,
+    EndpointConn,
+};
+use pyo3::{
+    pyclass,
+    pymethods,
+    types::{
+        PyAnyMethods,
+        PyByteArray,
+        PyByteArrayMethods,
+        PyBytes,
+        PyBytesMethods,
+    },
+    Bound,
+    PyAny,
+    PyErr,
+    PyResult,
+    Python,
+};
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+        ReadHalf,
+        WriteHalf,
+    },
+    sync::Mutex,
+};
+use tracing::debug;
+
+use crate::backend;
+
+// the most a single read will return, so a large n doesn't allocate up front
+const READ_CHUNK: usize = 64 * 1024;
+
+type BoxedConn = Box<dyn Conn>;
+
+/// The protocol of a connection, which is known differently for endpoint and edge connections.
+pub(crate) trait ConnProto {
+    fn conn_proto(&self) -> String;
+    /// Whether the connection carries the client's own TLS stream, which is passed
+    /// through to the upstream rather than wrapped in another TLS session.
+    fn tunnel_tls(&self, tls_termination: bool) -> bool;
+}
+
+impl ConnProto for EndpointConn {
+    fn conn_proto(&self) -> String {
+        self.proto().to_string()
+    }
+
+    fn tunnel_tls(&self, tls_termination: bool) -> bool {
+        self.proto() == "tls" && !tls_termination
+    }
+}
+
+impl ConnProto for EdgeConn {
+    fn conn_proto(&self) -> String {
+        match self.edge_type() {
+            EdgeType::Tcp => "tcp",
+            EdgeType::Tls => "tls",
+            EdgeType::Https => "https",
+            EdgeType::Undefined => "",
+        }
+        .to_string()
+    }
+
+    fn tunnel_tls(&self, _tls_termination: bool) -> bool {
+        self.edge_type() == EdgeType::Tls && self.passthrough_tls()
+    }
+}
+
+/// A single incoming connection accepted from a :class:`Listener`.
+#[pyclass]
+#[derive(Clone)]
+pub(crate) struct Connection {
+    remote_addr: String,
+    proto: String,
+    reader: Arc<Mutex<ReadHalf<BoxedConn>>>,
+    writer: Arc<Mutex<WriteHalf<BoxedConn>>>,
+}
+
+impl Connection {
+    pub(crate) fn new<C: Conn + ConnProto>(conn: C) -> Self {
+        let remote_addr = conn.remote_addr().to_string();
+        let proto = conn.conn_proto();
+        debug!("Accepted connection from {remote_addr}");
+        let (reader, writer) = tokio::io::split(Box::new(conn) as BoxedConn);
+        Connection {
+            remote_addr,
+            proto,
+            reader: Arc::new(Mutex::new(reader)),
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+
+    pub(crate) fn reader(&self) -> Arc<Mutex<ReadHalf<BoxedConn>>> {
+        self.reader.clone()
+    }
+
+    pub(crate) fn writer(&self) -> Arc<Mutex<WriteHalf<BoxedConn>>> {
+        self.writer.clone()
+    }
+}
+
+#[pymethods]
+#[allow(dead_code)]
+impl Connection {
+    fn __str__(&self) -> String {
+        format!("ngrok_connection({})", self.remote_addr)
+    }
+
+    /// The address of the client which opened this connection, e.g. "203.0.113.10:51234".
+    pub fn remote_addr(&self) -> String {
+        self.remote_addr.clone()
+    }
+
+    /// The protocol of the endpoint this connection arrived on, e.g. "https" or "tcp".
+    pub fn proto(&self) -> String {
+        self.proto.clone()
+    }
+
+    /// Read up to ``n`` bytes from the connection. If ``n`` is -1, read until EOF.
+    /// A single read returns at most 64 KiB. ``read(0)`` returns empty bytes at once, and
+    /// otherwise empty bytes mean EOF, that the remote side has closed the connection.
+    ///
+    /// :param int n: The maximum number of bytes to read, or -1 to read until EOF.
+    /// :return: A task to await for the bytes read.
+    #[pyo3(signature = (n=-1), text_signature = "(n=-1)")]
+    pub fn read<'a>(&self, py: Python<'a>, n: i64) -> PyResult<Bound<'a, PyAny>> {
+        let reader = self.reader.clone();
+        backend::future_into_py(py, async move {
+            if n == 0 {
+                return Ok(Python::with_gil(|py| PyBytes::new_bound(py, b"").unbind()));
+            }
+            let mut reader = reader.lock().await;
+            let mut buf = vec![];
+            if n < 0 {
+                reader.read_to_end(&mut buf).await?;
+            } else {
+                buf.resize((n as usize).min(READ_CHUNK), 0);
+                let len = reader.read(&mut buf).await?;
+                buf.truncate(len);
+            }
+            Ok(Python::with_gil(|py| PyBytes::new_bound(py, &buf).unbind()))
+        })
+    }
+
+    /// Write all of ``data`` to the connection.
+    ///
+    /// :param bytes data: The bytes to write.
+    /// :return: A task to await for the write to complete.
+    pub fn write<'a>(&self, py: Python<'a>, data: &Bound<'a, PyAny>) -> PyResult<Bound<'a, PyAny>> {
+        let data = bytes_from(data)?;
+        let writer = self.writer.clone();
+        backend::future_into_py(py, async move {
+            writer.lock().await.write_all(&data).await?;
+            Ok(())
+        })
+    }
+
+    /// Flush any buffered writes to the connection.
+    ///
+    /// :return: A task to await for the flush to complete.
+    pub fn drain<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let writer = self.writer.clone();
+        backend::future_into_py(py, async move {
+            writer.lock().await.flush().await?;
+            Ok(())
+        })
+    }
+
+    /// Close the connection, flushing any buffered writes first.
+    ///
+    /// :return: A task to await for the connection to close.
+    pub fn close<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let writer = self.writer.clone();
+        let remote_addr = self.remote_addr.clone();
+        backend::future_into_py(py, async move {
+            debug!("Closing connection from {remote_addr}");
+            writer.lock().await.shutdown().await?;
+            Ok(())
+        })
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        debug!("Connection drop");
+    }
+}
+
+/// Copy the contents of a bytes-like object.
+pub(crate) fn bytes_from(data: &Bound<'_, PyAny>) -> Result<Vec<u8>, PyErr> {
+    if let Ok(b) = data.downcast::<PyBytes>() {
+        return Ok(b.as_bytes().to_vec());
+    }
+    if let Ok(b) = data.downcast::<PyByteArray>() {
+        return Ok(b.to_vec());
+    }
+    data.extract::<Vec<u8>>()
+}
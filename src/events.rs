@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicU32,
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+        Weak,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use lazy_static::lazy_static;
+use ngrok::tunnel::AcceptError;
+use parking_lot::Mutex as SyncMutex;
+use pyo3::{
+    exceptions::PyStopAsyncIteration,
+    pyclass,
+    pymethods,
+    Bound,
+    PyAny,
+    PyErr,
+    PyRef,
+    PyResult,
+    Python,
+};
+use tokio::sync::{
+    broadcast::{
+        self,
+        error::RecvError,
+    },
+    Mutex,
+};
+use tracing::debug;
+
+use crate::{
+    backend,
+    listener::remove_session_listeners,
+    py_err,
+    ReconnectError,
+};
+
+// events retained for a slow iterator before it starts missing them
+const EVENT_BUFFER: usize = 64;
+
+lazy_static! {
+    // Monitors of connected sessions by session id, so listeners can report why their session ended.
+    static ref SESSION_MONITORS: SyncMutex<HashMap<String, Weak<SessionMonitor>>> =
+        SyncMutex::new(HashMap::new());
+}
+
+/// The lifecycle state of a session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SessionState {
+    Connecting,
+    Connected,
+    Reconnecting,
+    Closed,
+}
+
+impl SessionState {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            SessionState::Connecting => "connecting",
+            SessionState::Connected => "connected",
+            SessionState::Reconnecting => "reconnecting",
+            SessionState::Closed => "closed",
+        }
+    }
+}
+
+/// An event in the lifecycle of a :class:`Session`, yielded by :meth:`Session.events`.
+///
+/// ``kind`` is one of "connected", "disconnected", "reconnecting", "heartbeat",
+/// "stop_command", "restart_command", "update_command" or "closed". The remaining
+/// attributes are set only for the kinds they apply to.
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SessionEvent {
+    /// The kind of event.
+    #[pyo3(get)]
+    kind: &'static str,
+    /// The error which caused a "disconnected" or "closed" event.
+    #[pyo3(get)]
+    error: Option<String>,
+    /// The reconnect attempt number of a "reconnecting" event, starting at 1.
+    #[pyo3(get)]
+    attempt: Option<u32>,
+    /// The latency in milliseconds of a "heartbeat" event.
+    #[pyo3(get)]
+    latency: Option<u32>,
+    /// The requested version of an "update_command" event.
+    #[pyo3(get)]
+    version: Option<String>,
+    /// Whether an "update_command" event permits a major version change.
+    #[pyo3(get)]
+    permit_major_version: Option<bool>,
+}
+
+impl SessionEvent {
+    fn new(kind: &'static str) -> Self {
+        SessionEvent {
+            kind,
+            ..Default::default()
+        }
+    }
+}
+
+#[pymethods]
+impl SessionEvent {
+    fn __repr__(&self) -> String {
+        let mut fields = vec![format!("kind={:?}", self.kind)];
+        if let Some(error) = &self.error {
+            fields.push(format!("error={error:?}"));
+        }
+        if let Some(attempt) = self.attempt {
+            fields.push(format!("attempt={attempt}"));
+        }
+        if let Some(latency) = self.latency {
+            fields.push(format!("latency={latency}"));
+        }
+        if let Some(version) = &self.version {
+            fields.push(format!("version={version:?}"));
+        }
+        if let Some(permit) = self.permit_major_version {
+            fields.push(format!("permit_major_version={permit}"));
+        }
+        format!("SessionEvent({})", fields.join(", "))
+    }
+}
+
+/// Tracks the state of a session and broadcasts its lifecycle events.
+pub(crate) struct SessionMonitor {
+    state: SyncMutex<SessionState>,
+    attempt: AtomicU32,
+    // reconnect attempts over the life of the session, across outages
+    reconnects: AtomicU64,
+    heartbeat_latency: SyncMutex<Option<Duration>>,
+    events: broadcast::Sender<SessionEvent>,
+    session_id: SyncMutex<Option<String>>,
+    disconnected_at: SyncMutex<Option<Instant>>,
+    gave_up: SyncMutex<Option<String>>,
+}
+
+impl SessionMonitor {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(SessionMonitor {
+            state: SyncMutex::new(SessionState::Connecting),
+            attempt: AtomicU32::new(0),
+            reconnects: AtomicU64::new(0),
+            heartbeat_latency: SyncMutex::new(None),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            session_id: SyncMutex::new(None),
+            disconnected_at: SyncMutex::new(None),
+            gave_up: SyncMutex::new(None),
+        })
+    }
+
+    pub(crate) fn state(&self) -> SessionState {
+        *self.state.lock()
+    }
+
+    /// The total number of reconnect attempts the session has made.
+    pub(crate) fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// The latency of the most recent heartbeat, if one has been received.
+    pub(crate) fn heartbeat_latency(&self) -> Option<Duration> {
+        *self.heartbeat_latency.lock()
+    }
+
+    fn emit(&self, event: SessionEvent) {
+        debug!("Session event: {event:?}");
+        // no receivers is not an error, nobody is iterating events
+        let _ = self.events.send(event);
+    }
+
+    /// Record a reconnect attempt after the given error, returning the attempt number.
+    /// A failed reconnect continues the current outage, any other error starts a new one.
+    pub(crate) fn reconnecting(&self, error: &AcceptError) -> u32 {
+        *self.state.lock() = SessionState::Reconnecting;
+        if !matches!(error, AcceptError::Reconnect(_)) {
+            self.attempt.store(0, Ordering::SeqCst);
+            self.disconnected_at.lock().replace(Instant::now());
+            let mut event = SessionEvent::new("disconnected");
+            event.error = Some(error.to_string());
+            self.emit(event);
+        }
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        let attempt = self.attempt.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut event = SessionEvent::new("reconnecting");
+        event.attempt = Some(attempt);
+        self.emit(event);
+        attempt
+    }
+
+    /// Record that the session has connected to the ngrok service.
+    pub(crate) fn connected(&self) {
+        let mut state = self.state.lock();
+        if matches!(*state, SessionState::Connected | SessionState::Closed) {
+            return;
+        }
+        *state = SessionState::Connected;
+        drop(state);
+        self.disconnected_at.lock().take();
+        self.emit(SessionEvent::new("connected"));
+    }
+
+    /// How long the session has been disconnected, or zero if it is not.
+    pub(crate) fn outage(&self) -> Duration {
+        self.disconnected_at
+            .lock()
+            .map(|t| t.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Stop reconnecting, closing the session and releasing its listeners.
+    pub(crate) fn give_up(&self, reason: String) {
+        self.gave_up.lock().replace(reason.clone());
+        self.closed(Some(reason));
+        if let Some(id) = self.session_id.lock().clone() {
+            tokio::spawn(async move { remove_session_listeners(&id).await });
+        }
+    }
+
+    /// Record that the session has closed, optionally due to an error.
+    pub(crate) fn closed(&self, error: Option<String>) {
+        let mut state = self.state.lock();
+        if *state == SessionState::Closed {
+            return;
+        }
+        *state = SessionState::Closed;
+        drop(state);
+        let mut event = SessionEvent::new("closed");
+        event.error = error;
+        self.emit(event);
+    }
+
+    pub(crate) fn heartbeat(&self, latency: Option<Duration>) {
+        if latency.is_some() {
+            *self.heartbeat_latency.lock() = latency;
+        }
+        let mut event = SessionEvent::new("heartbeat");
+        event.latency = latency.and_then(|d| u32::try_from(d.as_millis()).ok());
+        self.emit(event);
+    }
+
+    pub(crate) fn stop_command(&self) {
+        self.emit(SessionEvent::new("stop_command"));
+    }
+
+    pub(crate) fn restart_command(&self) {
+        self.emit(SessionEvent::new("restart_command"));
+    }
+
+    pub(crate) fn update_command(&self, version: String, permit_major_version: bool) {
+        let mut event = SessionEvent::new("update_command");
+        event.version = Some(version);
+        event.permit_major_version = Some(permit_major_version);
+        self.emit(event);
+    }
+
+    /// The error to raise for the session giving up on reconnecting, if it has.
+    pub(crate) fn reconnect_error(&self) -> Option<PyErr> {
+        self.gave_up
+            .lock()
+            .as_ref()
+            .map(|reason| py_err::<ReconnectError>(reason.clone()))
+    }
+
+    pub(crate) fn subscribe(self: &Arc<Self>) -> SessionEvents {
+        SessionEvents {
+            rx: Arc::new(Mutex::new(self.events.subscribe())),
+            done: Arc::new(AtomicBool::new(self.state() == SessionState::Closed)),
+        }
+    }
+}
+
+/// Register the monitor of a newly connected session.
+pub(crate) fn register_monitor(session_id: String, monitor: &Arc<SessionMonitor>) {
+    monitor.session_id.lock().replace(session_id.clone());
+    let mut monitors = SESSION_MONITORS.lock();
+    monitors.retain(|_, m| m.strong_count() > 0);
+    monitors.insert(session_id, Arc::downgrade(monitor));
+}
+
+/// The monitors of all sessions which are still referenced, by session id.
+pub(crate) fn session_monitors() -> Vec<(String, Arc<SessionMonitor>)> {
+    SESSION_MONITORS
+        .lock()
+        .iter()
+        .filter_map(|(id, m)| Some((id.clone(), m.upgrade()?)))
+        .collect()
+}
+
+/// The error to raise if the given session gave up on reconnecting.
+pub(crate) fn reconnect_error(session_id: &str) -> Option<PyErr> {
+    let monitor = SESSION_MONITORS.lock().get(session_id)?.upgrade()?;
+    monitor.reconnect_error()
+}
+
+/// An async iterator over a :class:`Session`'s lifecycle events, ending after the
+/// session closes.
+#[pyclass]
+pub(crate) struct SessionEvents {
+    rx: Arc<Mutex<broadcast::Receiver<SessionEvent>>>,
+    done: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl SessionEvents {
+    fn __aiter__(self_: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        self_
+    }
+
+    fn __anext__<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let rx = self.rx.clone();
+        let done = self.done.clone();
+        backend::future_into_py(py, async move {
+            if done.load(Ordering::SeqCst) {
+                return Err(PyStopAsyncIteration::new_err(()));
+            }
+            let mut rx = rx.lock().await;
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if event.kind == "closed" {
+                            done.store(true, Ordering::SeqCst);
+                        }
+                        return Ok(event);
+                    }
+                    Err(RecvError::Lagged(n)) => debug!("Session events skipped {n} events"),
+                    Err(RecvError::Closed) => return Err(PyStopAsyncIteration::new_err(())),
+                }
+            }
+        })
+    }
+}
@@ -10,15 +10,22 @@ use pyo3::{
     pymethods,
     types::PyByteArray,
     PyRefMut,
+    PyResult,
 };
 
-use crate::listener_builder::HttpListenerBuilder;
+use crate::{
+    listener_builder::HttpListenerBuilder,
+    py_err,
+    ConfigError,
+};
 
 #[pymethods]
 #[allow(dead_code)]
 impl HttpListenerBuilder {
     /// The L7 protocol to use for this edge: "http1" or "http2".
     pub fn app_protocol(self_: PyRefMut<Self>, app_protocol: String) -> PyRefMut<Self> {
+        self_.record("app_protocol", app_protocol.as_str());
+        self_.set_upstream(|o| o.app_protocol = Some(app_protocol.clone()));
         self_.set(|b| {
             b.app_protocol(app_protocol);
         });
@@ -27,14 +34,15 @@ impl HttpListenerBuilder {
 
     /// The scheme that this edge should use.
     /// "HTTPS" or "HTTP", defaults to "HTTPS".
-    pub fn scheme(self_: PyRefMut<Self>, scheme: String) -> PyRefMut<Self> {
+    pub fn scheme(self_: PyRefMut<Self>, scheme: String) -> PyResult<PyRefMut<Self>> {
+        let name = scheme.to_uppercase();
+        let scheme = Scheme::from_str(scheme.as_str())
+            .map_err(|_| py_err::<ConfigError>(format!("Unknown scheme: {scheme:?}")))?;
+        self_.record("schemes", vec![name]);
         self_.set(|b| {
-            b.scheme(
-                Scheme::from_str(scheme.as_str())
-                    .unwrap_or_else(|_| panic!("Unknown scheme: {scheme:?}")),
-            );
+            b.scheme(scheme);
         });
-        self_
+        Ok(self_)
     }
     /// The domain to request for this edge, any valid domain or hostname that you have
     /// previously registered with ngrok. If using a custom domain, this requires
@@ -42,6 +50,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _ngrok dashboard: https://dashboard.ngrok.com/cloud-edge/domains
     pub fn domain(self_: PyRefMut<Self>, domain: String) -> PyRefMut<Self> {
+        self_.record("domain", domain.as_str());
         self_.set(|b| {
             b.domain(domain);
         });
@@ -55,6 +64,10 @@ impl HttpListenerBuilder {
         self_: PyRefMut<'a, Self>,
         mutual_tlsca: &PyByteArray,
     ) -> PyRefMut<'a, Self> {
+        self_.record_push(
+            "mutual_tls_cas",
+            String::from_utf8_lossy(&mutual_tlsca.to_vec()),
+        );
         self_.set(|b| {
             b.mutual_tlsca(Bytes::from(mutual_tlsca.to_vec()));
         });
@@ -65,6 +78,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Compression: https://ngrok.com/docs/cloud-edge/modules/compression/
     pub fn compression(self_: PyRefMut<Self>) -> PyRefMut<Self> {
+        self_.record("compression", true);
         self_.set(|b| {
             b.compression();
         });
@@ -72,6 +86,7 @@ impl HttpListenerBuilder {
     }
     /// Convert incoming websocket connections to TCP-like streams.
     pub fn websocket_tcp_conversion(self_: PyRefMut<Self>) -> PyRefMut<Self> {
+        self_.record("websocket_tcp_converter", true);
         self_.set(|b| {
             b.websocket_tcp_conversion();
         });
@@ -83,6 +98,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Circuit Breaker: https://ngrok.com/docs/cloud-edge/modules/circuit-breaker/
     pub fn circuit_breaker(self_: PyRefMut<Self>, circuit_breaker: f64) -> PyRefMut<Self> {
+        self_.record("circuit_breaker", circuit_breaker);
         self_.set(|b| {
             b.circuit_breaker(circuit_breaker);
         });
@@ -94,6 +110,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Request Headers: https://ngrok.com/docs/cloud-edge/modules/request-headers/
     pub fn request_header(self_: PyRefMut<Self>, name: String, value: String) -> PyRefMut<Self> {
+        self_.record_push("request_header_add", format!("{name}:{value}"));
         self_.set(|b| {
             b.request_header(name, value);
         });
@@ -104,6 +121,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Response Headers: https://ngrok.com/docs/cloud-edge/modules/response-headers/
     pub fn response_header(self_: PyRefMut<Self>, name: String, value: String) -> PyRefMut<Self> {
+        self_.record_push("response_header_add", format!("{name}:{value}"));
         self_.set(|b| {
             b.response_header(name, value);
         });
@@ -114,6 +132,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Request Headers: https://ngrok.com/docs/cloud-edge/modules/request-headers/
     pub fn remove_request_header(self_: PyRefMut<Self>, name: String) -> PyRefMut<Self> {
+        self_.record_push("request_header_remove", name.as_str());
         self_.set(|b| {
             b.remove_request_header(name);
         });
@@ -124,6 +143,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _Response Headers: https://ngrok.com/docs/cloud-edge/modules/response-headers/
     pub fn remove_response_header(self_: PyRefMut<Self>, name: String) -> PyRefMut<Self> {
+        self_.record_push("response_header_remove", name.as_str());
         self_.set(|b| {
             b.remove_response_header(name);
         });
@@ -133,6 +153,7 @@ impl HttpListenerBuilder {
     /// Credentials for basic authentication.
     /// If not called, basic authentication is disabled.
     pub fn basic_auth(self_: PyRefMut<Self>, username: String, password: String) -> PyRefMut<Self> {
+        self_.record_push("basic_auth", format!("{username}:{password}"));
         self_.set(|b| {
             b.basic_auth(username, password);
         });
@@ -146,6 +167,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _User Agent Filter: https://ngrok.com/docs/cloud-edge/modules/user-agent-filter/
     pub fn allow_user_agent(self_: PyRefMut<Self>, regex: String) -> PyRefMut<Self> {
+        self_.record_push("allow_user_agent", regex.as_str());
         self_.set(|b| {
             b.allow_user_agent(regex);
         });
@@ -158,6 +180,7 @@ impl HttpListenerBuilder {
     ///
     /// .. _User Agent Filter: https://ngrok.com/docs/cloud-edge/modules/user-agent-filter/
     pub fn deny_user_agent(self_: PyRefMut<Self>, regex: String) -> PyRefMut<Self> {
+        self_.record_push("deny_user_agent", regex.as_str());
         self_.set(|b| {
             b.deny_user_agent(regex);
         });
@@ -188,6 +211,12 @@ impl HttpListenerBuilder {
         client_id: Option<String>,
         client_secret: Option<String>,
     ) -> PyRefMut<Self> {
+        self_.record("oauth_provider", provider.as_str());
+        self_.record_opt("oauth_allow_emails", allow_emails.clone());
+        self_.record_opt("oauth_allow_domains", allow_domains.clone());
+        self_.record_opt("oauth_scopes", scopes.clone());
+        self_.record_opt("oauth_client_id", client_id.clone());
+        self_.record_opt("oauth_client_secret", client_secret.clone());
         let mut oauth = OauthOptions::new(provider);
         if let Some(allow_emails) = allow_emails {
             for v in allow_emails.iter() {
@@ -241,6 +270,12 @@ impl HttpListenerBuilder {
         allow_domains: Option<Vec<String>>,
         scopes: Option<Vec<String>>,
     ) -> PyRefMut<Self> {
+        self_.record("oidc_issuer_url", issuer_url.as_str());
+        self_.record("oidc_client_id", client_id.as_str());
+        self_.record("oidc_client_secret", client_secret.as_str());
+        self_.record_opt("oidc_allow_emails", allow_emails.clone());
+        self_.record_opt("oidc_allow_domains", allow_domains.clone());
+        self_.record_opt("oidc_scopes", scopes.clone());
         let mut oidc = OidcOptions::new(issuer_url, client_id, client_secret);
         if let Some(allow_emails) = allow_emails {
             for v in allow_emails.iter() {
@@ -266,6 +301,7 @@ impl HttpListenerBuilder {
 
     /// Enable endpoint pooling for this listener.
     pub fn pooling_enabled(self_: PyRefMut<Self>, pooling_enabled: bool) -> PyRefMut<Self> {
+        self_.record("pooling_enabled", pooling_enabled);
         self_.set(|b| {
             b.pooling_enabled(pooling_enabled);
         });
@@ -282,6 +318,8 @@ impl HttpListenerBuilder {
         provider: String,
         secret: String,
     ) -> PyRefMut<Self> {
+        self_.record("verify_webhook_provider", provider.as_str());
+        self_.record("verify_webhook_secret", secret.as_str());
         self_.set(|b| {
             b.webhook_verification(provider, secret);
         });
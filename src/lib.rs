@@ -1,4 +1,9 @@
 use ::ngrok::prelude::Error;
+use connection::Connection;
+use events::{
+    SessionEvent,
+    SessionEvents,
+};
 use listener::Listener;
 use listener_builder::{
     HttpListenerBuilder,
@@ -7,27 +12,42 @@ use listener_builder::{
     TlsListenerBuilder,
 };
 use pyo3::{
+    create_exception,
     exceptions::PyValueError,
     pymodule,
-    types::PyModule,
+    types::{
+        PyAnyMethods,
+        PyModule,
+    },
     wrap_pyfunction,
     PyErr,
     PyResult,
+    PyTypeInfo,
     Python,
 };
 use session::{
     Session,
     SessionBuilder,
 };
+use sync::{
+    SyncListener,
+    SyncSession,
+};
 use tracing::debug;
 
 use crate::{
+    config::{
+        async_forward_from_config,
+        forward_from_config,
+    },
     connect::{
         async_connect,
         async_disconnect,
+        async_forward_many,
         connect as connect_fn,
         disconnect,
         forward,
+        forward_many,
         kill,
     },
     listener::{
@@ -35,7 +55,25 @@ use crate::{
         get_listeners,
     },
     logging::log_level,
+    metrics::serve_metrics,
+    options::{
+        HttpOptions,
+        LabeledOptions,
+        TcpOptions,
+        TlsOptions,
+    },
+    policy::{
+        PolicyAction,
+        PolicyRule,
+        TrafficPolicy,
+    },
+    runtime::{
+        configure_runtime,
+        runtime_info,
+    },
     session::set_auth_token,
+    shutdown::install_signal_handlers,
+    streams::start_server,
     wrapper::{
         default,
         fd,
@@ -46,14 +84,27 @@ use crate::{
     },
 };
 
+pub mod backend;
+pub mod config;
 pub mod connect;
+pub mod connection;
+pub mod events;
 pub mod http;
 pub mod listener;
 pub mod listener_builder;
 pub mod logging;
+pub mod metrics;
+pub mod options;
+pub mod policy;
+pub mod reconnect;
+pub mod runtime;
 pub mod session;
+pub mod shutdown;
+pub mod streams;
+pub mod sync;
 pub mod tcp;
 pub mod tls;
+pub mod upstream;
 pub mod wrapper;
 
 // A Python module implemented in Rust. The name of this function must match
@@ -63,32 +114,75 @@ pub mod wrapper;
 /// The ngrok Agent SDK for Python
 #[pymodule]
 fn ngrok(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    runtime::init();
+
     m.add_function(wrap_pyfunction!(async_connect, m)?)?;
     m.add_function(wrap_pyfunction!(async_disconnect, m)?)?;
+    m.add_function(wrap_pyfunction!(async_forward_from_config, m)?)?;
+    m.add_function(wrap_pyfunction!(async_forward_many, m)?)?;
     m.add_function(wrap_pyfunction!(async_listeners, m)?)?;
+    m.add_function(wrap_pyfunction!(configure_runtime, m)?)?;
     m.add_function(wrap_pyfunction!(connect_fn, m)?)?;
     m.add_function(wrap_pyfunction!(default, m)?)?;
     m.add_function(wrap_pyfunction!(disconnect, m)?)?;
     m.add_function(wrap_pyfunction!(fd, m)?)?;
     m.add_function(wrap_pyfunction!(forward, m)?)?;
+    m.add_function(wrap_pyfunction!(forward_from_config, m)?)?;
+    m.add_function(wrap_pyfunction!(forward_many, m)?)?;
     m.add_function(wrap_pyfunction!(getsockname, m)?)?;
+    m.add_function(wrap_pyfunction!(install_signal_handlers, m)?)?;
     m.add_function(wrap_pyfunction!(kill, m)?)?;
     m.add_function(wrap_pyfunction!(listen, m)?)?;
     m.add_function(wrap_pyfunction!(log_level, m)?)?;
     m.add_function(wrap_pyfunction!(pipe_name, m)?)?;
+    m.add_function(wrap_pyfunction!(runtime_info, m)?)?;
+    m.add_function(wrap_pyfunction!(serve_metrics, m)?)?;
     m.add_function(wrap_pyfunction!(set_auth_token, m)?)?;
+    m.add_function(wrap_pyfunction!(start_server, m)?)?;
     m.add_function(wrap_pyfunction!(get_listeners, m)?)?;
     m.add_function(wrap_pyfunction!(werkzeug_develop, m)?)?;
 
+    shutdown::register_finalizer(py)?;
+
     m.add_class::<SessionBuilder>()?;
     m.add_class::<Session>()?;
+    m.add_class::<SyncSession>()?;
+    m.add_class::<SessionEvent>()?;
+    m.add_class::<SessionEvents>()?;
 
     m.add_class::<Listener>()?;
+    m.add_class::<SyncListener>()?;
+    m.add_class::<Connection>()?;
     m.add_class::<HttpListenerBuilder>()?;
     m.add_class::<LabeledListenerBuilder>()?;
     m.add_class::<TcpListenerBuilder>()?;
     m.add_class::<TlsListenerBuilder>()?;
 
+    m.add_class::<HttpOptions>()?;
+    m.add_class::<LabeledOptions>()?;
+    m.add_class::<TcpOptions>()?;
+    m.add_class::<TlsOptions>()?;
+
+    m.add_class::<PolicyAction>()?;
+    m.add_class::<PolicyRule>()?;
+    m.add_class::<TrafficPolicy>()?;
+
+    m.add("NgrokError", py.get_type_bound::<NgrokError>())?;
+    m.add("SessionError", py.get_type_bound::<SessionError>())?;
+    m.add("ReconnectError", py.get_type_bound::<ReconnectError>())?;
+    m.add("AuthError", py.get_type_bound::<AuthError>())?;
+    m.add("ListenerError", py.get_type_bound::<ListenerError>())?;
+    m.add("ForwardError", py.get_type_bound::<ForwardError>())?;
+    m.add("ConfigError", py.get_type_bound::<ConfigError>())?;
+    // default the typed attributes so user-raised errors can be inspected the same way
+    let base = py.get_type_bound::<NgrokError>();
+    base.setattr("error_code", py.None())?;
+    base.setattr("server_message", py.None())?;
+    base.setattr("context", py.None())?;
+    let config = py.get_type_bound::<ConfigError>();
+    config.setattr("line", py.None())?;
+    config.setattr("column", py.None())?;
+
     // turn on logging bridge by default, since user won't see unless they activate Python logging
     if let Err(e) = log_level(py, None) {
         debug!("Error enabling logging: {e:?}")
@@ -96,17 +190,91 @@ fn ngrok(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     Ok(())
 }
 
-// Shorthand for PyValueError creation
-pub(crate) fn py_err(message: impl Into<String>) -> PyErr {
-    PyValueError::new_err(message.into())
+create_exception!(
+    ngrok,
+    NgrokError,
+    PyValueError,
+    "Base class for errors raised by the ngrok SDK. Has ``error_code``, ``server_message`` and ``context`` attributes."
+);
+create_exception!(
+    ngrok,
+    SessionError,
+    NgrokError,
+    "An error establishing, using or closing a Session."
+);
+create_exception!(
+    ngrok,
+    ReconnectError,
+    SessionError,
+    "A Session stopped reconnecting to the ngrok service because its reconnect policy was exhausted."
+);
+create_exception!(
+    ngrok,
+    AuthError,
+    NgrokError,
+    "The ngrok service rejected the Session's credentials."
+);
+create_exception!(
+    ngrok,
+    ListenerError,
+    NgrokError,
+    "An error starting, looking up or closing a Listener."
+);
+create_exception!(
+    ngrok,
+    ForwardError,
+    NgrokError,
+    "An error forwarding Listener traffic to an upstream."
+);
+create_exception!(
+    ngrok,
+    ConfigError,
+    NgrokError,
+    "Invalid configuration was provided to a builder or to forward()."
+);
+
+// Shorthand for creating an SDK error of type E from a local failure
+pub(crate) fn py_err<E: PyTypeInfo>(message: impl Into<String>) -> PyErr {
+    new_err::<E>(message.into(), None, None)
+}
+
+// Shorthand for creating an SDK error of type E from an upstream ngrok Error
+pub(crate) fn py_ngrok_err<E: PyTypeInfo>(message: impl Into<String>, err: &impl Error) -> PyErr {
+    new_err::<E>(
+        message.into(),
+        Some(err.msg()),
+        err.error_code().map(|c| c.to_string()),
+    )
 }
 
-// Shorthand for Error creation from NgrokError
-pub(crate) fn py_ngrok_err(message: impl Into<String>, err: &impl Error) -> PyErr {
-    let py_err = if let Some(error_code) = err.error_code() {
-        PyValueError::new_err((message.into(), err.msg(), error_code.to_string()))
-    } else {
-        PyValueError::new_err((message.into(), err.msg()))
-    };
-    py_err
+// Build the error, keeping the (context, server_message, error_code) args layout
+// of the original ValueErrors, and exposing each part as a typed attribute.
+fn new_err<E: PyTypeInfo>(
+    context: String,
+    server_message: Option<String>,
+    error_code: Option<String>,
+) -> PyErr {
+    Python::with_gil(|py| {
+        let err = match (&server_message, &error_code) {
+            (Some(msg), Some(code)) => PyErr::from_type_bound(
+                E::type_object_bound(py),
+                (context.clone(), msg.clone(), code.clone()),
+            ),
+            (Some(msg), None) => {
+                PyErr::from_type_bound(E::type_object_bound(py), (context.clone(), msg.clone()))
+            }
+            _ => PyErr::from_type_bound(E::type_object_bound(py), context.clone()),
+        };
+        let value = err.value_bound(py);
+        for (name, attr) in [
+            ("error_code", error_code),
+            ("server_message", server_message),
+            ("context", Some(context)),
+        ] {
+            if let Err(e) = value.setattr(name, attr) {
+                debug!("Error setting {name} on exception: {e:?}");
+            }
+        }
+        err
+    })
 }
@@ -3,7 +3,14 @@ use std::{
     collections::HashMap,
     error::Error as StdError,
     io,
-    sync::Arc,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
 };
 
 // the lib.name and the pymodule below need to be 'ngrok' for that to be the python library
@@ -17,9 +24,9 @@ use async_trait::async_trait;
 use futures::prelude::*;
 use lazy_static::lazy_static;
 use ngrok::{
-    forwarder::Forwarder,
     session::ConnectError,
     tunnel::{
+        AcceptError,
         HttpTunnel,
         LabeledTunnel,
         TlsTunnel,
@@ -27,6 +34,7 @@ use ngrok::{
 };
 use once_cell::sync::Lazy;
 use pyo3::{
+    exceptions::PyStopAsyncIteration,
     intern,
     prelude::*,
     pyclass,
@@ -39,6 +47,10 @@ use pyo3::{
     Bound,
 };
 use regex::Regex;
+use serde_json::{
+    Map,
+    Value,
+};
 use tokio::{
     sync::Mutex,
     task::JoinHandle,
@@ -46,19 +58,34 @@ use tokio::{
 use tracing::{
     debug,
     info,
+    warn,
 };
 use url::Url;
 
 #[cfg(target_os = "windows")]
 use crate::wrapper::wrap_object;
 use crate::{
+    backend,
+    connect,
+    connection::Connection,
+    events::reconnect_error,
     py_err,
     py_ngrok_err,
+    upstream::{
+        self,
+        ListenerMetrics,
+        UpstreamForwarder,
+        UpstreamOptions,
+        UpstreamPool,
+    },
     wrapper::{
         self,
         bound_default_tcp_socket,
         bound_default_unix_socket,
     },
+    ConfigError,
+    ForwardError,
+    ListenerError,
 };
 
 /// Python dictionary of id's to sockets.
@@ -79,10 +106,23 @@ lazy_static! {
 struct Storage {
     listener: Option<Arc<Mutex<dyn ExtendedListener>>>,
     forwarder: Option<Arc<Mutex<dyn ExtendedForwarder>>>,
+    // set once forward() owns the accept loop, so accept() can fail fast instead of waiting on the lock
+    forwarding: AtomicBool,
     session: Session,
     tun_meta: Arc<ListenerInfo>,
+    upstream: UpstreamOptions,
+    // the builder settings the listener was started with, by forward() option name, which
+    // reconfigure starts its replacement from. They may hold secrets, so they're only kept
+    // for a listener reconfigure can replace, and are cleared on close.
+    options: parking_lot::Mutex<Map<String, Value>>,
+    // the upstreams connections are forwarded to, set before forwarding starts
+    target: Target,
+    metrics: Arc<ListenerMetrics>,
 }
 
+/// The pool a listener forwards to, shared by its storage and Listener objects.
+type Target = Arc<parking_lot::Mutex<Option<Arc<UpstreamPool>>>>;
+
 struct ListenerInfo {
     id: String,
     forwards_to: String,
@@ -95,8 +135,14 @@ struct ListenerInfo {
 /// The upstream object cannot be turned into an object since it contains generics, so implementing
 /// a proxy trait without generics which can be the dyn type stored in the global map.
 #[async_trait]
-pub trait ExtendedListener: Send {
-    async fn fwd(&mut self, url: Url) -> CoreResult<(), io::Error>;
+pub(crate) trait ExtendedListener: Send {
+    async fn fwd(
+        &mut self,
+        pool: Arc<UpstreamPool>,
+        options: UpstreamOptions,
+        metrics: Arc<ListenerMetrics>,
+    ) -> CoreResult<(), io::Error>;
+    async fn accept_conn(&mut self) -> CoreResult<Option<Connection>, AcceptError>;
 }
 
 pub trait ExtendedForwarder: Send {
@@ -109,6 +155,8 @@ pub trait ExtendedForwarder: Send {
 pub(crate) struct Listener {
     session: Session,
     tun_meta: Arc<ListenerInfo>,
+    metrics: Arc<ListenerMetrics>,
+    target: Target,
 }
 
 macro_rules! make_listener_type {
@@ -122,7 +170,7 @@ macro_rules! make_listener_type {
 
         #[allow(dead_code)]
         impl $wrapper {
-            pub(crate) async fn new_listener(session: Session, raw_listener: $listener) -> Listener {
+            pub(crate) async fn new_listener(session: Session, raw_listener: $listener, upstream: UpstreamOptions, options: Map<String, Value>) -> Listener {
                 let id = raw_listener.id().to_string();
                 let tun_meta = Arc::new(ListenerInfo {
                     id: id.clone(),
@@ -137,31 +185,43 @@ macro_rules! make_listener_type {
                 let storage = Arc::new(Storage {
                     listener: Some(Arc::new(Mutex::new(raw_listener))),
                     forwarder: None,
+                    forwarding: AtomicBool::new(false),
                     session,
                     tun_meta,
+                    upstream,
+                    options: parking_lot::Mutex::new(pooled_options(options)),
+                    target: Default::default(),
+                    metrics: Default::default(),
                 });
                 GLOBAL_LISTENERS.lock().await.insert(id, storage.clone());
                 // create the user-facing object
                 Listener::from_storage(&storage)
             }
 
-            pub(crate) async fn new_forwarder(session: Session, forwarder: Forwarder<$listener>) -> Listener {
-                let id = forwarder.id().to_string();
+            pub(crate) async fn new_forwarder(session: Session, raw_listener: $listener, pool: Arc<UpstreamPool>, upstream: UpstreamOptions, options: Map<String, Value>) -> Listener {
+                let id = raw_listener.id().to_string();
                 let tun_meta = Arc::new(ListenerInfo {
                     id: id.clone(),
-                    forwards_to: forwarder.forwards_to().to_string(),
-                    metadata: forwarder.metadata().to_string(),
-                    url: Some(forwarder.url().to_string()),
-                    proto: Some(forwarder.proto().to_string()),
+                    forwards_to: raw_listener.forwards_to().to_string(),
+                    metadata: raw_listener.metadata().to_string(),
+                    url: Some(raw_listener.url().to_string()),
+                    proto: Some(raw_listener.proto().to_string()),
                     labels: HashMap::new(),
                 });
-                info!("Created listener {id:?} with url {:?}", forwarder.url());
+                info!("Created listener {id:?} with url {:?}", raw_listener.url());
                 // keep a listener reference until an explicit call to close to prevent python gc dropping it
+                let metrics = Arc::new(ListenerMetrics::default());
+                let forwarder = UpstreamForwarder::spawn(raw_listener, pool.clone(), upstream.clone(), metrics.clone());
                 let storage = Arc::new(Storage {
                     listener: None,
                     forwarder: Some(Arc::new(Mutex::new(forwarder))),
+                    forwarding: AtomicBool::new(true),
                     session,
                     tun_meta,
+                    upstream,
+                    options: parking_lot::Mutex::new(pooled_options(options)),
+                    target: Arc::new(parking_lot::Mutex::new(Some(pool))),
+                    metrics,
                 });
                 GLOBAL_LISTENERS.lock().await.insert(id, storage.clone());
                 // create the user-facing object
@@ -181,7 +241,7 @@ macro_rules! make_listener_type {
 
         #[allow(dead_code)]
         impl $wrapper {
-            pub(crate) async fn new_listener(session: Session, raw_listener: $listener) -> Listener {
+            pub(crate) async fn new_listener(session: Session, raw_listener: $listener, upstream: UpstreamOptions, options: Map<String, Value>) -> Listener {
                 let id = raw_listener.id().to_string();
                 let tun_meta = Arc::new(ListenerInfo {
                     id: id.clone(),
@@ -196,31 +256,43 @@ macro_rules! make_listener_type {
                 let storage = Arc::new(Storage {
                     listener: Some(Arc::new(Mutex::new(raw_listener))),
                     forwarder: None,
+                    forwarding: AtomicBool::new(false),
                     session,
                     tun_meta,
+                    upstream,
+                    options: parking_lot::Mutex::new(options),
+                    target: Default::default(),
+                    metrics: Default::default(),
                 });
                 GLOBAL_LISTENERS.lock().await.insert(id, storage.clone());
                 // create the user-facing object
                 Listener::from_storage(&storage)
             }
 
-            pub(crate) async fn new_forwarder(session: Session, forwarder: Forwarder<$listener>) -> Listener {
-                let id = forwarder.id().to_string();
+            pub(crate) async fn new_forwarder(session: Session, raw_listener: $listener, pool: Arc<UpstreamPool>, upstream: UpstreamOptions, options: Map<String, Value>) -> Listener {
+                let id = raw_listener.id().to_string();
                 let tun_meta = Arc::new(ListenerInfo {
                     id: id.clone(),
-                    forwards_to: forwarder.forwards_to().to_string(),
-                    metadata: forwarder.metadata().to_string(),
+                    forwards_to: raw_listener.forwards_to().to_string(),
+                    metadata: raw_listener.metadata().to_string(),
                     url: None,
                     proto: None,
-                    labels: forwarder.labels().clone(),
+                    labels: raw_listener.labels().clone(),
                 });
                 info!("Created listener {id:?} with labels {:?}", tun_meta.labels);
                 // keep a listener reference until an explicit call to close to prevent python gc dropping it
+                let metrics = Arc::new(ListenerMetrics::default());
+                let forwarder = UpstreamForwarder::spawn(raw_listener, pool.clone(), upstream.clone(), metrics.clone());
                 let storage = Arc::new(Storage {
                     listener: None,
                     forwarder: Some(Arc::new(Mutex::new(forwarder))),
+                    forwarding: AtomicBool::new(true),
                     session,
                     tun_meta,
+                    upstream,
+                    options: parking_lot::Mutex::new(options),
+                    target: Arc::new(parking_lot::Mutex::new(Some(pool))),
+                    metrics,
                 });
                 GLOBAL_LISTENERS.lock().await.insert(id, storage.clone());
                 // create the user-facing object
@@ -235,27 +307,110 @@ macro_rules! make_listener_type {
     ($wrapper:ident, $listener:tt) => {
         #[async_trait]
         impl ExtendedListener for $listener {
-            #[allow(deprecated)]
-            async fn fwd(&mut self, url: Url) -> CoreResult<(), io::Error> {
-                ngrok::prelude::TunnelExt::forward(self, url).await
+            async fn fwd(
+                &mut self,
+                pool: Arc<UpstreamPool>,
+                options: UpstreamOptions,
+                metrics: Arc<ListenerMetrics>,
+            ) -> CoreResult<(), io::Error> {
+                upstream::forward_tunnel(self, pool, options, metrics).await
             }
-        }
 
-        impl ExtendedForwarder for Forwarder<$listener> {
-            fn get_join(&mut self) -> &mut JoinHandle<CoreResult<(), Box<dyn StdError + Send + Sync>>> {
-                self.join()
+            async fn accept_conn(&mut self) -> CoreResult<Option<Connection>, AcceptError> {
+                Ok(self.try_next().await?.map(Connection::new))
             }
         }
     };
 }
 
 impl Listener {
+    pub(crate) async fn async_close(&self) -> PyResult<()> {
+        self.async_close_drain(None).await
+    }
+
+    /// Close the listener, first waiting up to drain_timeout for the connections it is
+    /// forwarding to finish.
+    pub(crate) async fn async_close_drain(&self, drain_timeout: Option<Duration>) -> PyResult<()> {
+        let id = self.tun_meta.id.clone();
+        debug!("Listener closing, id: {id:?}");
+
+        // we may not be able to lock our reference to the listener due to the forward_* calls which
+        // continuously accept-loop while the listener is active, so calling close on the Session.
+        // This stops new connections, while those already forwarding carry on.
+        let res = self
+            .session
+            .close_tunnel(id.clone())
+            .await
+            .map_err(|e| py_ngrok_err::<ListenerError>("error closing listener", &e));
+
+        if let Some(drain_timeout) = drain_timeout {
+            if tokio::time::timeout(drain_timeout, self.metrics.idle())
+                .await
+                .is_err()
+            {
+                info!(
+                    "Listener {id:?} drain timed out with {} active connections",
+                    self.metrics.active_count()
+                );
+            }
+            self.metrics.abort();
+        }
+
+        // drop our internal reference to the listener after awaiting close
+        remove_global_listener(&id).await?;
+
+        res
+    }
+
+    /// Forward incoming connections to the address in the background, whether or not an
+    /// asyncio loop is running. As nothing awaits the forwarding, its errors are logged as
+    /// warnings, while closing the listener ends it without one.
+    pub(crate) fn spawn_forward(&self, addr: String) -> PyResult<()> {
+        let id = self.tun_meta.id.clone();
+        let pool = self.forward_target(addr)?;
+        pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            if let Err(e) = forward(&id, pool).await {
+                warn!("Error forwarding listener {id:?}: {e}");
+            }
+        });
+        Ok(())
+    }
+
+    /// Set the upstream this listener forwards to before forwarding starts, so reconfigure
+    /// finds it as soon as the listener is returned.
+    fn forward_target(&self, addr: String) -> PyResult<Arc<UpstreamPool>> {
+        let url = upstream_url(addr)?;
+        info!(
+            "Listener {:?} forwarding to {:?}",
+            self.tun_meta.id,
+            url.to_string()
+        );
+        let pool = Arc::new(UpstreamPool::single(url));
+        self.target.lock().replace(pool.clone());
+        Ok(pool)
+    }
+
+    /// The session this listener belongs to.
+    pub(crate) fn raw_session(&self) -> Session {
+        self.session.clone()
+    }
+
+    /// Close the listener unless it was already closed, or went down with its session.
+    pub(crate) async fn close_if_open(&self) -> PyResult<()> {
+        if get_storage_by_id(&self.tun_meta.id).await.is_err() {
+            return Ok(());
+        }
+        self.async_close().await
+    }
+
     /// Create Listener from Storage
     fn from_storage(storage: &Arc<Storage>) -> Listener {
         // create the user-facing object
         Listener {
             session: storage.session.clone(),
             tun_meta: storage.tun_meta.clone(),
+            metrics: storage.metrics.clone(),
+            target: storage.target.clone(),
         }
     }
 }
@@ -306,52 +461,253 @@ impl Listener {
     /// For file socket paths on Linux/Darwin, addr can be a unix domain socket path, e.g. "/tmp/ngrok.sock".
     pub fn forward<'a>(&self, py: Python<'a>, addr: String) -> PyResult<Bound<'a, PyAny>> {
         let id = self.tun_meta.id.clone();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move { forward(&id, addr).await })
+        let pool = self.forward_target(addr)?;
+        backend::future_into_py(py, async move { forward(&id, pool).await })
+    }
+
+    /// Traffic counters for the connections this listener has forwarded, as a dict with
+    /// the keys "accepted", "active", "bytes_in", "bytes_out", "forward_errors" and
+    /// "last_connection", the unix timestamp of the latest connection or None.
+    ///
+    /// Connections are only counted when the listener forwards them, via
+    /// :any:`listen_and_forward`, :any:`listen_and_serve` or :meth:`forward`.
+    pub fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.metrics.to_dict(py)
     }
 
     /// Wait for the forwarding task to exit.
     pub fn join<'a>(&mut self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
         let id = self.tun_meta.id.clone();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            let forwarder_option = &get_storage_by_id(&id).await?.forwarder;
-            if let Some(forwarder_mutex) = forwarder_option {
+        backend::future_into_py(py, async move {
+            let storage = get_storage_by_id(&id).await?;
+            if let Some(forwarder_mutex) = &storage.forwarder {
                 forwarder_mutex
                     .lock()
                     .await
                     .get_join()
                     .fuse()
                     .await
-                    .map_err(|e| py_err(format!("error on join: {e:?}")))?
-                    .map_err(|e| py_err(format!("error on join: {e:?}")))
+                    .map_err(|e| py_err::<ForwardError>(format!("error on join: {e:?}")))?
+                    .map_err(|e| {
+                        reconnect_error(&storage.session.id()).unwrap_or_else(|| {
+                            py_err::<ForwardError>(format!("error on join: {e:?}"))
+                        })
+                    })
             } else {
-                Err(py_err("Listener is not joinable"))
+                Err(py_err::<ListenerError>("Listener is not joinable"))
+            }
+        })
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Accept the next incoming connection, ending iteration once the listener is closed.
+    fn __anext__<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let id = self.tun_meta.id.clone();
+        backend::future_into_py(py, async move {
+            if get_storage_by_id(&id).await.is_err() {
+                return Err(PyStopAsyncIteration::new_err(()));
+            }
+            match accept_connection(&id).await {
+                Ok(Some(conn)) => Ok(conn),
+                Ok(None) => Err(PyStopAsyncIteration::new_err(())),
+                // closed while waiting, which ends iteration as a close before it does
+                Err(_) if get_storage_by_id(&id).await.is_err() => {
+                    Err(PyStopAsyncIteration::new_err(()))
+                }
+                Err(e) => Err(e),
             }
         })
     }
 
+    /// Replace this listener with a new one started with the given options, keeping its
+    /// public address and upstream, so the URL stays up across configuration changes such as
+    /// new CIDR restrictions or a new traffic policy. The options are those of
+    /// :meth:`forward`, and replace the same options this listener was started with while
+    /// the others are kept. An option given as None is dropped. Session options are ignored.
+    ///
+    /// The replacement is started with endpoint pooling for the same domain, or remote
+    /// address for TCP, and forwards to the same upstream. Once it is up, this listener is
+    /// closed. This listener must itself have been started with pooling enabled, otherwise
+    /// a :class:`ListenerError` is raised and this listener is left running.
+    ///
+    /// :param options: The options for the replacement listener.
+    /// :return: A task to await for the replacement :class:`Listener`.
+    #[pyo3(signature = (**options))]
+    pub fn reconfigure<'a>(
+        &self,
+        py: Python<'a>,
+        options: Option<&PyDict>,
+    ) -> PyResult<Bound<'a, PyAny>> {
+        let kwargs = match options {
+            Some(options) => options.copy()?,
+            None => PyDict::new(py),
+        };
+        // prepare_options drops the Nones, which here drop the setting being replaced
+        let mut dropped = vec![];
+        for (k, v) in kwargs.iter() {
+            if v.is_none() {
+                dropped.push(k.extract::<String>()?.replace('.', "_"));
+            }
+        }
+        if kwargs.contains("addr")? {
+            return Err(py_err::<ConfigError>(
+                "reconfigure keeps forwarding to the same upstream, addr cannot be changed",
+            ));
+        }
+        let proto = match self.tun_meta.proto.as_deref() {
+            None => "labeled",
+            Some("https") => "http",
+            Some(proto) => proto,
+        };
+        if let Some(p) = kwargs.get_item("proto")? {
+            if connect::get_string("proto", p)? != proto {
+                return Err(py_err::<ConfigError>(format!(
+                    "reconfigure cannot change the proto of a {proto:?} listener"
+                )));
+            }
+        }
+        let kwargs = connect::prepare_options(py, None, Some(proto.to_string()), kwargs)?;
+        // the upstream is carried over from this listener instead
+        kwargs.del_item("addr")?;
+
+        // keep the public address
+        let url = self
+            .tun_meta
+            .url
+            .as_deref()
+            .map(Url::parse)
+            .transpose()
+            .map_err(|e| py_err::<ListenerError>(format!("cannot parse listener url: {e}")))?;
+        let host = url.as_ref().and_then(|u| u.host_str());
+        match (proto, host) {
+            ("http" | "tls", Some(host)) => {
+                if !kwargs.contains("domain")? && !kwargs.contains("hostname")? {
+                    kwargs.set_item("domain", host)?;
+                }
+            }
+            ("tcp", Some(host)) => {
+                if !kwargs.contains("remote_addr")? {
+                    let port = url.as_ref().and_then(|u| u.port()).unwrap_or_default();
+                    kwargs.set_item("remote_addr", format!("{host}:{port}"))?;
+                }
+            }
+            _ => {
+                if !kwargs.contains("labels")? {
+                    let labels: Vec<String> = self
+                        .tun_meta
+                        .labels
+                        .iter()
+                        .map(|(k, v)| format!("{k}:{v}"))
+                        .collect();
+                    kwargs.set_item("labels", labels)?;
+                }
+            }
+        }
+        let pooled = proto != "labeled";
+        if pooled {
+            kwargs.set_item("pooling_enabled", true)?;
+        }
+        let forwards_to = match kwargs.contains("forwards_to")? {
+            true => None,
+            false => Some(self.tun_meta.forwards_to.clone()),
+        };
+
+        let listener = self.clone();
+        let options: Py<PyDict> = kwargs.into();
+        backend::future_into_py(py, async move {
+            let storage = get_storage_by_id(&listener.tun_meta.id).await?;
+            // ngrok would reject the replacement of an unpooled endpoint
+            let settings = storage.options.lock().clone();
+            if pooled && settings.get("pooling_enabled") != Some(&Value::Bool(true)) {
+                return Err(py_err::<ListenerError>(
+                    "Listener was not started with pooling_enabled, it cannot be reconfigured",
+                ));
+            }
+            let pool = storage.target.lock().clone().ok_or_else(|| {
+                py_err::<ListenerError>("Listener is not forwarding, there is no upstream to keep")
+            })?;
+            let options =
+                Python::with_gil(|py| merge_options(py, &settings, options.bind(py), &dropped))?;
+            let replacement =
+                connect::forward_with_options(&listener.session, options, pool, forwards_to)
+                    .await?;
+            info!(
+                "Listener {:?} replaced by {:?}",
+                listener.tun_meta.id,
+                replacement.id()
+            );
+            // the replacement is already serving, so a failed close shouldn't lose it
+            if let Err(e) = listener.async_close().await {
+                warn!(
+                    "Error closing replaced listener {:?}: {e}",
+                    listener.tun_meta.id
+                );
+            }
+            Ok(replacement)
+        })
+    }
+
     /// Close the listener.
     ///
     /// This is an RPC call that must be `.await`ed.
     /// It is equivalent to calling `Session::close_listener` with this
     /// listener's ID.
-    pub fn close<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
-        let session = self.session.clone();
-        let id = self.tun_meta.id.clone();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            debug!("{} closing, id: {id:?}", stringify!($wrapper));
+    ///
+    /// With a ``drain_timeout``, the listener stops accepting new connections but waits up
+    /// to that many seconds for the connections it is forwarding to finish, before cutting
+    /// any which remain.
+    ///
+    /// :param float or None drain_timeout: The seconds to wait for forwarded connections to finish.
+    #[pyo3(signature = (drain_timeout=None))]
+    pub fn close<'a>(
+        &self,
+        py: Python<'a>,
+        drain_timeout: Option<f64>,
+    ) -> PyResult<Bound<'a, PyAny>> {
+        let drain_timeout = drain_timeout.map(drain_duration).transpose()?;
+        let listener = self.clone();
+        backend::future_into_py(py, async move {
+            listener.async_close_drain(drain_timeout).await
+        })
+    }
 
-            // we may not be able to lock our reference to the listener due to the forward_* calls which
-            // continuously accept-loop while the listener is active, so calling close on the Session.
-            let res = session
-                .close_tunnel(id.clone())
-                .await
-                .map_err(|e| py_ngrok_err("error closing listener", &e));
+    fn __aenter__<'a>(slf: PyRef<'a, Self>, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let listener: Py<Self> = slf.into();
+        backend::future_into_py(py, async move { Ok(listener) })
+    }
 
-            // drop our internal reference to the listener after awaiting close
-            remove_global_listener(&id).await?;
+    /// Close the listener on leaving an ``async with`` block.
+    fn __aexit__<'a>(
+        &self,
+        py: Python<'a>,
+        _exc_type: &Bound<'a, PyAny>,
+        _exc_value: &Bound<'a, PyAny>,
+        _traceback: &Bound<'a, PyAny>,
+    ) -> PyResult<Bound<'a, PyAny>> {
+        let listener = self.clone();
+        backend::future_into_py(
+            py,
+            async move { listener.close_if_open().await.map(|_| false) },
+        )
+    }
 
-            res
-        })
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Close the listener on leaving a ``with`` block.
+    fn __exit__(
+        &self,
+        py: Python,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<bool> {
+        let listener = self.clone();
+        wrapper::block_on(py, async move { listener.close_if_open().await }).map(|_| false)
     }
 }
 
@@ -388,9 +744,25 @@ impl Listener {
         self.get_sock_attr(py, attr)?.call0(py)
     }
 
+    /// Accept the next incoming connection on this listener, returning a task to await
+    /// for a :class:`Connection`.
+    ///
+    /// When the listener is being used as a socket, such as after calling :meth:`listen`
+    /// or reading :attr:`fd`, this instead accepts on that socket.
+    ///
+    /// :return: A task to await for the next :class:`Connection`.
     pub fn accept(&self, py: Python) -> PyResult<Py<PyAny>> {
-        let attr = intern!(py, "accept").as_gil_ref();
-        self.get_sock_attr(py, attr)?.call0(py)
+        if self.has_sock(py)? {
+            let attr = intern!(py, "accept").as_gil_ref();
+            return self.get_sock_attr(py, attr)?.call0(py);
+        }
+        let id = self.tun_meta.id.clone();
+        backend::future_into_py(py, async move {
+            accept_connection(&id)
+                .await?
+                .ok_or_else(|| py_err::<ListenerError>("Listener is closed"))
+        })
+        .map(|f| f.unbind())
     }
 
     pub fn gettimeout(&self, py: Python) -> PyResult<Py<PyAny>> {
@@ -418,13 +790,13 @@ impl Listener {
             if self.get_family(py)?.as_ref(py).eq(af_unix)? {
                 // unix socket
                 let sockname_str: &PyString = sockname.downcast(py)?;
-                self.forward(py, format!("{UNIX_PREFIX}{sockname_str}"))?;
+                self.spawn_forward(format!("{UNIX_PREFIX}{sockname_str}"))?;
                 return result;
             }
         }
         // fallback to tcp
         let sockname_tuple: &PyTuple = sockname.downcast(py)?;
-        self.forward(py, format!("localhost:{}", sockname_tuple.get_item(1)?))?;
+        self.spawn_forward(format!("localhost:{}", sockname_tuple.get_item(1)?))?;
         result
     }
 
@@ -440,6 +812,11 @@ impl Listener {
         self.get_sock(py)?.getattr(py, attr)
     }
 
+    fn has_sock(&self, py: Python) -> PyResult<bool> {
+        let map: &PyDict = SOCK_CELL.as_ref(py);
+        map.contains(&self.tun_meta.id)
+    }
+
     fn get_sock(&self, py: Python) -> PyResult<Py<PyAny>> {
         let map: &PyDict = SOCK_CELL.as_ref(py);
         let maybe_socket = map.get_item(&self.tun_meta.id);
@@ -499,34 +876,106 @@ make_listener_type! {
     LabeledListener, LabeledTunnel, label
 }
 
-pub async fn forward(id: &String, mut addr: String) -> PyResult<()> {
-    let tun_option = &get_storage_by_id(id).await?.listener;
-    if let Some(tun) = tun_option {
-        // if addr is not a full url, choose a default protocol
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"^[a-z0-9\-\.]+:\d+$").unwrap();
-        }
-        if !addr.contains(':') || RE.find(&addr).is_some() {
-            if addr.contains('/') {
-                addr = format!("{UNIX_PREFIX}{addr}")
-            } else {
-                addr = format!("{TCP_PREFIX}{addr}")
-            }
+/// The url of an upstream address, choosing a default scheme when it has none.
+fn upstream_url(mut addr: String) -> PyResult<Url> {
+    // if addr is not a full url, choose a default protocol
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^[a-z0-9\-\.]+:\d+$").unwrap();
+    }
+    if !addr.contains(':') || RE.find(&addr).is_some() {
+        if addr.contains('/') {
+            addr = format!("{UNIX_PREFIX}{addr}")
+        } else {
+            addr = format!("{TCP_PREFIX}{addr}")
         }
-        // parse to a url
-        let url = Url::parse(addr.as_str())
-            .map_err(|e| py_err(format!("Cannot parse address: {addr}, error: {e}")))?;
+    }
+    // parse to a url
+    Url::parse(addr.as_str())
+        .map_err(|e| py_err::<ConfigError>(format!("Cannot parse address: {addr}, error: {e}")))
+}
 
-        info!("Listener {id:?} forwarding to {:?}", url.to_string());
-        let res = tun.lock().await.fwd(url).await;
+pub(crate) async fn forward(id: &String, pool: Arc<UpstreamPool>) -> PyResult<()> {
+    let storage = get_storage_by_id(id).await?;
+    if let Some(tun) = &storage.listener {
+        storage.forwarding.store(true, Ordering::SeqCst);
+        let res = tun
+            .lock()
+            .await
+            .fwd(pool, storage.upstream.clone(), storage.metrics.clone())
+            .await;
 
         debug!("forward returning");
+        if res.is_err() {
+            if let Some(err) = reconnect_error(&storage.session.id()) {
+                return Err(err);
+            }
+        }
         canceled_is_ok(res)
     } else {
-        Err(py_err("listener is not forwardable"))
+        Err(py_err::<ForwardError>("listener is not forwardable"))
+    }
+}
+
+/// Accept the next connection on a listener, or None if the listener has stopped.
+pub(crate) async fn accept_connection(id: &String) -> PyResult<Option<Connection>> {
+    let storage = get_storage_by_id(id).await?;
+    let tun = storage.listener.as_ref().ok_or_else(|| {
+        py_err::<ListenerError>("Listener is not acceptable, it was created to forward")
+    })?;
+    if storage.forwarding.load(Ordering::SeqCst) {
+        return Err(py_err::<ListenerError>(
+            "Listener is not acceptable, it is already forwarding",
+        ));
+    }
+    let res = tun.lock().await.accept_conn().await;
+    res.map_err(|e| {
+        reconnect_error(&storage.session.id())
+            .unwrap_or_else(|| py_err::<ListenerError>(format!("error accepting connection: {e}")))
+    })
+}
+
+/// The settings to keep for reconfigure, which are none unless the listener was started with
+/// pooling, since only then can it be replaced.
+fn pooled_options(options: Map<String, Value>) -> Map<String, Value> {
+    match options.get("pooling_enabled") {
+        Some(Value::Bool(true)) => options,
+        _ => Map::new(),
     }
 }
 
+/// The settings a listener was started with, with the reconfigure options given over them
+/// and the dropped ones removed. An option given by an alias replaces the setting it names.
+fn merge_options(
+    py: Python,
+    settings: &Map<String, Value>,
+    options: &Bound<'_, PyDict>,
+    dropped: &[String],
+) -> PyResult<Py<PyDict>> {
+    let json = Value::Object(settings.clone()).to_string();
+    let merged = PyModule::import_bound(py, "json")?
+        .call_method1("loads", (json,))?
+        .downcast_into::<PyDict>()?;
+    for (alias, target) in [("hostname", "domain"), ("policy", "traffic_policy")] {
+        if options.contains(alias)? || dropped.iter().any(|k| k == alias) {
+            merged.del_item(target).ok();
+        }
+    }
+    for key in dropped {
+        merged.del_item(key).ok();
+    }
+    merged.update(options.as_mapping())?;
+    Ok(merged.unbind())
+}
+
+/// Convert a drain timeout in seconds.
+pub(crate) fn drain_duration(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        py_err::<ConfigError>(format!(
+            "drain_timeout must be a non-negative number of seconds, got {seconds}"
+        ))
+    })
+}
+
 fn canceled_is_ok(input: CoreResult<(), io::Error>) -> PyResult<()> {
     match input {
         Ok(_) => Ok(()),
@@ -541,7 +990,7 @@ fn canceled_is_ok(input: CoreResult<(), io::Error>) -> PyResult<()> {
                 }
             }
 
-            Err(py_err(format!("error forwarding: {e:?}")))
+            Err(py_err::<ForwardError>(format!("error forwarding: {e:?}")))
         }
     }
 }
@@ -553,13 +1002,31 @@ async fn get_storage_by_id(id: &String) -> PyResult<Arc<Storage>> {
         .lock()
         .await
         .get(id)
-        .ok_or(py_err("Listener is no longer running"))?
+        .ok_or(py_err::<ListenerError>("Listener is no longer running"))?
         .clone()) // required clone
 }
 
+/// Close and forget the sockets of all listeners.
+pub(crate) fn release_sockets(py: Python) {
+    // nothing to release if no socket was ever created
+    let Some(sockets) = Lazy::get(&SOCK_CELL) else {
+        return;
+    };
+    let dict = sockets.bind(py);
+    for (id, sock) in dict.iter() {
+        if let Err(e) = sock.call_method0("close") {
+            debug!("Error closing socket {id}: {e:?}");
+        }
+    }
+    dict.clear();
+}
+
 /// Delete any reference to the listener id
 pub(crate) async fn remove_global_listener(id: &String) -> PyResult<()> {
-    GLOBAL_LISTENERS.lock().await.remove(id);
+    // a forward task may still hold the storage, so drop the settings and their secrets now
+    if let Some(storage) = GLOBAL_LISTENERS.lock().await.remove(id) {
+        storage.options.lock().clear();
+    }
 
     // remove any references to sockets
     Python::with_gil(|py| -> PyResult<()> {
@@ -578,6 +1045,22 @@ pub(crate) async fn remove_global_listener(id: &String) -> PyResult<()> {
     })
 }
 
+/// Delete any reference to the listeners of the given session.
+pub(crate) async fn remove_session_listeners(session_id: &str) {
+    let ids: Vec<String> = GLOBAL_LISTENERS
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, storage)| storage.session.id() == session_id)
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in ids {
+        if let Err(e) = remove_global_listener(&id).await {
+            debug!("Error removing listener {id}: {e:?}");
+        }
+    }
+}
+
 /// Close a listener with the given url, or all listeners if no url is defined.
 #[allow(dead_code)]
 pub(crate) async fn close_url(url: Option<String>) -> PyResult<()> {
@@ -591,7 +1074,7 @@ pub(crate) async fn close_url(url: Option<String>) -> PyResult<()> {
                 .session
                 .close_tunnel(id)
                 .await
-                .map_err(|e| py_ngrok_err("error closing listener", &e))?;
+                .map_err(|e| py_ngrok_err::<ListenerError>("error closing listener", &e))?;
             close_ids.push(id.clone());
         }
     }
@@ -604,6 +1087,34 @@ pub(crate) async fn close_url(url: Option<String>) -> PyResult<()> {
     Ok(())
 }
 
+/// The traffic metrics of a running listener.
+pub(crate) struct ListenerStats {
+    pub(crate) id: String,
+    pub(crate) session_id: String,
+    pub(crate) proto: String,
+    pub(crate) metrics: Arc<ListenerMetrics>,
+}
+
+/// Collect the metrics of every running listener.
+pub(crate) async fn listener_stats() -> Vec<ListenerStats> {
+    GLOBAL_LISTENERS
+        .lock()
+        .await
+        .iter()
+        .map(|(id, storage)| ListenerStats {
+            id: id.clone(),
+            session_id: storage.session.id(),
+            // labeled listeners have no endpoint protocol of their own
+            proto: storage
+                .tun_meta
+                .proto
+                .clone()
+                .unwrap_or_else(|| "labeled".to_string()),
+            metrics: storage.metrics.clone(),
+        })
+        .collect()
+}
+
 /// Make a list of all Listeners by iterating over the global Listener map and creating an Listener from each.
 pub(crate) async fn list_listeners(session_id: Option<String>) -> PyResult<Vec<Listener>> {
     let mut listeners: Vec<Listener> = vec![];
@@ -624,12 +1135,12 @@ pub(crate) async fn list_listeners(session_id: Option<String>) -> PyResult<Vec<L
 #[pyfunction]
 pub fn get_listeners(py: Python) -> PyResult<Py<PyAny>> {
     // move to async, handling if there is an async loop running or not
-    wrapper::loop_wrap(py, None, "    return await ngrok.async_listeners()")
+    wrapper::bridge(py, list_listeners(None))
 }
 
 #[pyfunction]
 pub fn async_listeners(py: Python) -> PyResult<Bound<'_, PyAny>> {
-    pyo3_async_runtimes::tokio::future_into_py(py, async move { list_listeners(None).await })
+    backend::future_into_py(py, async move { list_listeners(None).await })
 }
 
 // Helper class to implement the iterator protocol for listener sockets.
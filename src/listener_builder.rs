@@ -1,4 +1,5 @@
 use std::{
+    future::Future,
     str::FromStr,
     sync::Arc,
 };
@@ -11,6 +12,14 @@ use parking_lot::Mutex;
 use pyo3::{
     pyclass,
     pymethods,
+    types::{
+        PyAnyMethods,
+        PyDict,
+        PyDictMethods,
+        PyModule,
+        PyString,
+        PyStringMethods,
+    },
     Bound,
     Py,
     PyAny,
@@ -18,10 +27,18 @@ use pyo3::{
     PyResult,
     Python,
 };
-use tracing::debug;
+use serde_json::{
+    Map,
+    Value,
+};
+use tracing::{
+    debug,
+    warn,
+};
 use url::Url;
 
 use crate::{
+    backend,
     listener::{
         HttpListener,
         LabeledListener,
@@ -29,11 +46,107 @@ use crate::{
         TcpListener,
         TlsListener,
     },
+    policy::{
+        self,
+        PolicyConfig,
+    },
     py_err,
     py_ngrok_err,
-    wrapper::address_from_server,
+    sync::SyncListener,
+    upstream::{
+        Strategy,
+        UpstreamOptions,
+        UpstreamPool,
+    },
+    wrapper::{
+        address_from_server,
+        block_on_timeout_cleanup,
+    },
+    ConfigError,
+    ListenerError,
 };
 
+/// Shown by config() in place of secrets.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Settings which are secrets in their entirety.
+const SECRETS: &[&str] = &[
+    "key",
+    "oauth_client_secret",
+    "oidc_client_secret",
+    "verify_webhook_secret",
+];
+
+/// The settings with their secrets redacted, for config(). Basic auth credentials keep
+/// their user names.
+fn redacted(mut settings: Map<String, Value>) -> Map<String, Value> {
+    for (key, value) in settings.iter_mut() {
+        match (key.as_str(), value) {
+            ("basic_auth", Value::Array(credentials)) => {
+                for credential in credentials {
+                    let user = credential
+                        .as_str()
+                        .and_then(|c| c.split_once(':'))
+                        .map(|(user, _)| user.to_string());
+                    if let Some(user) = user {
+                        *credential = format!("{user}:{REDACTED}").into();
+                    }
+                }
+            }
+            ("traffic_policy", Value::String(policy)) => *policy = policy::redact(policy),
+            (key, value) if SECRETS.contains(&key) => *value = REDACTED.into(),
+            _ => {}
+        }
+    }
+    settings
+}
+
+/// Close a listener which started after a blocking listen gave up on it, so its endpoint
+/// isn't left online with nothing to own it.
+async fn close_late(listener: Listener) {
+    warn!(
+        "Closing listener {:?} which started after its listen timed out",
+        listener.id()
+    );
+    if let Err(e) = listener.close_if_open().await {
+        warn!("Error closing listener {:?}: {e}", listener.id());
+    }
+}
+
+/// The urls of a listen_and_forward argument, which may be a single url or a list of them.
+fn url_list(to_url: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    if let Ok(url) = to_url.downcast::<PyString>() {
+        return Ok(vec![url.to_cow()?.into_owned()]);
+    }
+    to_url.extract::<Vec<String>>().map_err(|_| {
+        py_err::<ConfigError>("Url forward argument must be a url string or a list of url strings")
+    })
+}
+
+/// A pool of the upstream urls to forward to, and a description of them for forwards_to.
+fn upstream_pool(
+    to_urls: Vec<String>,
+    strategy: Strategy,
+) -> PyResult<(Arc<UpstreamPool>, String)> {
+    if to_urls.is_empty() {
+        return Err(py_err::<ConfigError>(
+            "Url forward argument must not be empty",
+        ));
+    }
+    let urls = to_urls
+        .iter()
+        .map(|to_url| {
+            Url::parse(to_url).map_err(|e| {
+                py_err::<ConfigError>(format!("Url forward argument parse failure, {e}"))
+            })
+        })
+        .collect::<PyResult<Vec<Url>>>()?;
+    Ok((
+        Arc::new(UpstreamPool::new(urls, strategy)),
+        to_urls.join(", "),
+    ))
+}
+
 macro_rules! make_listener_builder {
     ($(#[$outer:meta])* $wrapper:ident, $builder:tt, $listener:tt, $mode:tt) => {
         $(#[$outer])*
@@ -42,6 +155,10 @@ macro_rules! make_listener_builder {
         pub(crate) struct $wrapper {
             session: Arc<Mutex<Session>>,
             pub(crate) listener_builder: Arc<Mutex<$builder>>,
+            // how listen_and_forward and forward connect to the upstream service
+            upstream: Arc<Mutex<UpstreamOptions>>,
+            // what has been configured, by forward() option name, for config() and reconfigure
+            settings: Arc<Mutex<Map<String, Value>>>,
         }
 
         #[pymethods]
@@ -49,26 +166,33 @@ macro_rules! make_listener_builder {
         impl $wrapper {
             /// Listener-specific opaque metadata. Viewable via the API.
             pub fn metadata(self_: PyRefMut<Self>, metadata: String) -> PyRefMut<Self> {
+                self_.record("metadata", metadata.as_str());
                 self_.set(|b| {b.metadata(metadata);});
                 self_
             }
 
             /// Whether to disable certificate verification for this listener.
             pub fn verify_upstream_tls(self_: PyRefMut<Self>, verify_upstream_tls: bool) -> PyRefMut<Self> {
+                self_.record("verify_upstream_tls", verify_upstream_tls);
                 self_.set(|b| {b.verify_upstream_tls(verify_upstream_tls);});
+                self_.set_upstream(|o| o.verify_upstream_tls = verify_upstream_tls);
                 self_
             }
 
             /// Begin listening for new connections on this listener.
             pub fn listen<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
-                let session = self.session.lock().clone();
-                let tun = self.listener_builder.lock().clone();
-                pyo3_async_runtimes::tokio::future_into_py(
-                    py,
-                    async move {
-                        $wrapper::do_listen(session, tun).await
-                    },
-                )
+                backend::future_into_py(py, self.listening())
+            }
+
+            /// Begin listening for new connections on this listener, blocking until it is up.
+            ///
+            /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+            /// :return: The started listener.
+            /// :rtype: SyncListener
+            #[pyo3(signature = (timeout=None))]
+            pub fn listen_sync(&self, py: Python, timeout: Option<f64>) -> PyResult<SyncListener> {
+                block_on_timeout_cleanup(py, timeout, "listen", self.listening(), close_late)
+                    .map(SyncListener::from)
             }
 
             /// Begin listening for new connections on this listener and forwarding them to the given url.
@@ -76,29 +200,37 @@ macro_rules! make_listener_builder {
             /// "http://localhost:8080", "https://192.168.1.100:8443", or for file socket paths on
             /// Linux/Darwin "unix:///path/to/unix.sock".
             ///
-            /// :param to_url: The URL to forward traffic on to
+            /// A list of urls spreads connections over several upstreams according to the strategy:
+            /// "failover" uses the first healthy upstream in list order, "round_robin" uses each
+            /// healthy upstream in turn, and "least_connections" uses the healthy upstream with the
+            /// fewest active connections. An upstream which refuses a connection is skipped for a
+            /// short time, and the connection is retried on the next one.
+            ///
+            /// :param to_url: The URL, or list of URLs, to forward traffic on to
+            /// :param str strategy: How to choose between several URLs, defaults to "failover"
             /// :return: A task to await for the :class:`Listener` linked with the server.
             /// :rtype: Task
-            pub fn listen_and_forward<'a>(&self, to_url: String, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
-                let url = Url::parse(&to_url).map_err(|e| py_err(format!("Url forward argument parse failure, {e}")))?;
-                let session = self.session.lock().clone();
-                let builder = self.listener_builder.lock().clone();
-
-                pyo3_async_runtimes::tokio::future_into_py(
-                    py,
-                    async move {
-                        let result = builder
-                        .listen_and_forward(url)
-                        .await
-                        .map_err(|e| py_ngrok_err("failed to start listener", &e));
+            #[pyo3(signature = (to_url, strategy="failover"))]
+            pub fn listen_and_forward<'a>(&self, to_url: &Bound<'a, PyAny>, py: Python<'a>, strategy: &str) -> PyResult<Bound<'a, PyAny>> {
+                let (pool, forwards_to) = upstream_pool(url_list(to_url)?, Strategy::parse(strategy)?)?;
+                backend::future_into_py(py, self.forwarding(pool, Some(forwards_to)))
+            }
 
-                        // create the wrapping listener object via its async new()
-                        match result {
-                            Ok(raw_fwd) => Ok($listener::new_forwarder(session, raw_fwd).await),
-                            Err(val) => Err(val),
-                        }
-                    },
-                )
+            /// Begin listening for new connections on this listener and forwarding them to the
+            /// given url, or list of urls, as :meth:`listen_and_forward` does, blocking until the
+            /// listener is up.
+            ///
+            /// :param to_url: The URL, or list of URLs, to forward traffic on to
+            /// :param str strategy: How to choose between several URLs, defaults to "failover"
+            /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+            /// :return: The started listener.
+            /// :rtype: SyncListener
+            #[pyo3(signature = (to_url, strategy="failover", timeout=None))]
+            pub fn listen_and_forward_sync(&self, py: Python, to_url: &Bound<'_, PyAny>, strategy: &str, timeout: Option<f64>) -> PyResult<SyncListener> {
+                let (pool, forwards_to) = upstream_pool(url_list(to_url)?, Strategy::parse(strategy)?)?;
+                let forwarding = self.forwarding(pool, Some(forwards_to));
+                block_on_timeout_cleanup(py, timeout, "listen_and_forward", forwarding, close_late)
+                    .map(SyncListener::from)
             }
 
             /// Begin listening for new connections on this listener and forwarding them to the given http server.
@@ -113,7 +245,41 @@ macro_rules! make_listener_builder {
                 server: Py<PyAny>,
             ) -> PyResult<Bound<'a, PyAny>> {
                 let address = address_from_server(py, server)?;
-                return self.listen_and_forward(address, py)
+                let (pool, forwards_to) = upstream_pool(vec![address], Strategy::Failover)?;
+                backend::future_into_py(py, self.forwarding(pool, Some(forwards_to)))
+            }
+
+            /// The settings configured on this builder so far, keyed by the matching option
+            /// names of :meth:`forward`. Secrets such as passwords and private keys are redacted,
+            /// including those in traffic policy actions, whose policy is given as JSON text.
+            ///
+            /// :return: The configured settings.
+            /// :rtype: dict
+            pub fn config(&self, py: Python) -> PyResult<Py<PyAny>> {
+                let json = Value::Object(redacted(self.settings.lock().clone())).to_string();
+                Ok(PyModule::import_bound(py, "json")?.call_method1("loads", (json,))?.unbind())
+            }
+
+            /// A new builder with the same session and settings as this one, which can be
+            /// configured further without affecting this one.
+            ///
+            /// :return: The copied builder.
+            pub fn copy(&self) -> Self {
+                $wrapper {
+                    session: Arc::new(Mutex::new(self.session.lock().clone())),
+                    listener_builder: Arc::new(Mutex::new(self.listener_builder.lock().clone())),
+                    upstream: Arc::new(Mutex::new(self.upstream.lock().clone())),
+                    settings: Arc::new(Mutex::new(self.settings.lock().clone())),
+                }
+            }
+
+            pub fn __repr__(&self, py: Python) -> PyResult<String> {
+                let config = self.config(py)?;
+                let mut parts = vec![];
+                for (k, v) in config.bind(py).downcast::<PyDict>()?.iter() {
+                    parts.push(format!("{}={}", k.str()?, v.repr()?));
+                }
+                Ok(format!("{}({})", stringify!($wrapper), parts.join(", ")))
             }
         }
 
@@ -123,9 +289,61 @@ macro_rules! make_listener_builder {
                 $wrapper {
                     session: Arc::new(Mutex::new(session)),
                     listener_builder: Arc::new(Mutex::new(raw_listener_builder)),
+                    upstream: Default::default(),
+                    settings: Default::default(),
                 }
             }
 
+            /// Start a listener, in a future which doesn't borrow the builder.
+            fn listening(&self) -> impl Future<Output = PyResult<Listener>> + Send + 'static {
+                let session = self.session.lock().clone();
+                let builder = self.listener_builder.lock().clone();
+                let upstream = self.upstream.lock().clone();
+                let options = self.settings.lock().clone();
+                $wrapper::do_listen(session, builder, upstream, options)
+            }
+
+            /// Start a listener forwarding to the pool, in a future which doesn't borrow the builder.
+            fn forwarding(&self, pool: Arc<UpstreamPool>, forwards_to: Option<String>) -> impl Future<Output = PyResult<Listener>> + Send + 'static {
+                let session = self.session.lock().clone();
+                let builder = self.listener_builder.lock().clone();
+                let upstream = self.upstream.lock().clone();
+                let options = self.settings.lock().clone();
+                $wrapper::do_forward(session, builder, pool, upstream, options, forwards_to)
+            }
+
+            /// Record a setting for config(), replacing any earlier value.
+            pub(crate) fn record(&self, key: &str, value: impl Into<Value>) {
+                self.settings.lock().insert(key.to_string(), value.into());
+            }
+
+            /// Record an optional setting for config(), forgetting any earlier value if unset.
+            pub(crate) fn record_opt(&self, key: &str, value: Option<impl Into<Value>>) {
+                match value {
+                    Some(value) => self.record(key, value),
+                    None => {
+                        self.settings.lock().remove(key);
+                    }
+                }
+            }
+
+            /// Record a setting for config() which accumulates over repeated calls.
+            pub(crate) fn record_push(&self, key: &str, value: impl Into<Value>) {
+                let mut settings = self.settings.lock();
+                let entry = settings.entry(key).or_insert_with(|| Value::Array(vec![]));
+                if let Value::Array(values) = entry {
+                    values.push(value.into());
+                }
+            }
+
+            /// Update the options used to connect to the upstream service.
+            pub(crate) fn set_upstream<F>(&self, f: F)
+            where
+                F: FnOnce(&mut UpstreamOptions),
+            {
+                f(&mut self.upstream.lock());
+            }
+
             /// Handle the locking and Option management
             pub(crate) fn set<F>(&self, f: F)
             where
@@ -136,20 +354,40 @@ macro_rules! make_listener_builder {
             }
 
             pub(crate) async fn async_listen(&self) -> PyResult<Listener> {
-                let session = self.session.lock().clone();
-                let tun = self.listener_builder.lock().clone();
-                $wrapper::do_listen(session, tun).await
+                self.listening().await
+            }
+
+            /// Start a listener forwarding to an existing pool of upstreams, setting its
+            /// forwards_to if given.
+            pub(crate) async fn async_forward(&self, pool: Arc<UpstreamPool>, forwards_to: Option<String>) -> PyResult<Listener> {
+                self.forwarding(pool, forwards_to).await
             }
 
-            async fn do_listen(session: Session, builder: $builder) -> PyResult<Listener> {
+            async fn do_forward(session: Session, mut builder: $builder, pool: Arc<UpstreamPool>, upstream: UpstreamOptions, options: Map<String, Value>, forwards_to: Option<String>) -> PyResult<Listener> {
+                if let Some(forwards_to) = forwards_to {
+                    builder.forwards_to(forwards_to);
+                }
                 let result = builder
                             .listen()
                             .await
-                            .map_err(|e| py_ngrok_err("failed to start listener", &e));
+                            .map_err(|e| py_ngrok_err::<ListenerError>("failed to start listener", &e));
 
                 // create the wrapping listener object via its async new()
                 match result {
-                    Ok(raw_tun) => Ok($listener::new_listener(session, raw_tun).await),
+                    Ok(raw_tun) => Ok($listener::new_forwarder(session, raw_tun, pool, upstream, options).await),
+                    Err(val) => Err(val),
+                }
+            }
+
+            async fn do_listen(session: Session, builder: $builder, upstream: UpstreamOptions, options: Map<String, Value>) -> PyResult<Listener> {
+                let result = builder
+                            .listen()
+                            .await
+                            .map_err(|e| py_ngrok_err::<ListenerError>("failed to start listener", &e));
+
+                // create the wrapping listener object via its async new()
+                match result {
+                    Ok(raw_tun) => Ok($listener::new_listener(session, raw_tun, upstream, options).await),
                     Err(val) => Err(val),
                 }
             }
@@ -176,6 +414,7 @@ macro_rules! make_listener_builder {
             ///
             /// .. _IP restrictions: https://ngrok.com/docs/cloud-edge/modules/ip-restrictions/
             pub fn allow_cidr(self_: PyRefMut<Self>, cidr: String) -> PyRefMut<Self> {
+                self_.record_push("allow_cidr", cidr.as_str());
                 self_.set(|b| {b.allow_cidr(cidr);});
                 self_
             }
@@ -185,16 +424,18 @@ macro_rules! make_listener_builder {
             ///
             /// .. _IP restrictions: https://ngrok.com/docs/cloud-edge/modules/ip-restrictions/
             pub fn deny_cidr(self_: PyRefMut<Self>, cidr: String) -> PyRefMut<Self> {
+                self_.record_push("deny_cidr", cidr.as_str());
                 self_.set(|b| {b.deny_cidr(cidr);});
                 self_
             }
             /// The version of PROXY protocol to use with this listener "1", "2", or "" if not using.
-            pub fn proxy_proto(self_: PyRefMut<Self>, proxy_proto: String) -> PyRefMut<Self> {
-                self_.set(|b| {b.proxy_proto(
-                    ProxyProto::from_str(proxy_proto.as_str())
-                        .unwrap_or_else(|_| panic!("Unknown proxy protocol: {:?}", proxy_proto)),
-                );});
-                self_
+            pub fn proxy_proto(self_: PyRefMut<Self>, proxy_proto: String) -> PyResult<PyRefMut<Self>> {
+                let proto = ProxyProto::from_str(proxy_proto.as_str())
+                    .map_err(|_| py_err::<ConfigError>(format!("Unknown proxy protocol: {:?}", proxy_proto)))?;
+                self_.record("proxy_proto", proxy_proto);
+                self_.set(|b| {b.proxy_proto(proto);});
+                self_.set_upstream(|o| o.proxy_proto = proto != ProxyProto::None);
+                Ok(self_)
             }
             /// Listener backend metadata. Viewable via the dashboard and API, but has no
             /// bearing on listener behavior.
@@ -202,20 +443,24 @@ macro_rules! make_listener_builder {
             /// To automatically forward connections, you can use :any:`listen_and_forward`,
             /// or :any:`listen_and_serve` on the Listener Builder.
             pub fn forwards_to(self_: PyRefMut<Self>, forwards_to: String) -> PyRefMut<Self> {
+                self_.record("forwards_to", forwards_to.as_str());
                 self_.set(|b| {b.forwards_to(forwards_to);});
                 self_
             }
 
             /// DEPRECATED: use traffic_policy instead.
-            /// :param str policy_config: Traffic policy configuration to be attached to the listener.
-            pub fn policy(self_: PyRefMut<Self>, policy_config: String) -> PyRefMut<Self> {
-                self_.set(|b| {b.traffic_policy(policy_config);});
-                self_
+            /// :param policy_config: Traffic policy configuration to be attached to the listener.
+            /// :type policy_config: str or TrafficPolicy
+            pub fn policy(self_: PyRefMut<Self>, policy_config: PolicyConfig) -> PyRefMut<Self> {
+                Self::traffic_policy(self_, policy_config)
             }
 
-            /// Traffic Policy configuration.
-            /// :param str policy_config: Traffic policy configuration to be attached to the listener.
-            pub fn traffic_policy(self_: PyRefMut<Self>, policy_config: String) -> PyRefMut<Self> {
+            /// Traffic Policy configuration, as JSON or YAML text or a :class:`TrafficPolicy`.
+            /// :param policy_config: Traffic policy configuration to be attached to the listener.
+            /// :type policy_config: str or TrafficPolicy
+            pub fn traffic_policy(self_: PyRefMut<Self>, policy_config: PolicyConfig) -> PyRefMut<Self> {
+                let policy_config = policy_config.0;
+                self_.record("traffic_policy", policy_config.as_str());
                 self_.set(|b| {b.traffic_policy(policy_config);});
                 self_
             }
@@ -231,12 +476,15 @@ macro_rules! make_listener_builder {
             ///
             /// .. _Using Labels: https://ngrok.com/docs/guides/using-labels-within-ngrok/
             pub fn label(self_: PyRefMut<Self>, label: String, value: String) -> PyRefMut<Self> {
+                self_.record_push("labels", format!("{label}:{value}"));
                 self_.set(|b| {b.label(label, value);});
                 self_
             }
 
             /// Set the L7 application protocol used for this listener, i.e. "http1" or "http2" (default "http1")
             pub fn app_protocol(self_: PyRefMut<Self>, app_protocol: String) -> PyRefMut<Self> {
+                self_.record("app_protocol", app_protocol.as_str());
+                self_.set_upstream(|o| o.app_protocol = Some(app_protocol.clone()));
                 self_.set(|b| {b.app_protocol(app_protocol);});
                 self_
             }
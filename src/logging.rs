@@ -9,7 +9,10 @@ use pyo3_log::{
     Logger,
 };
 
-use crate::py_err;
+use crate::{
+    py_err,
+    ConfigError,
+};
 
 /// Set the log level for the bridge to Python logging.
 /// The log level defaults to INFO, it can be set to one of ERROR, WARN, INFO, DEBUG, or TRACE.
@@ -26,7 +29,11 @@ pub fn log_level(py: Python, level: Option<String>) -> PyResult<()> {
             "INFO" => LevelFilter::Info,
             "DEBUG" => LevelFilter::Debug,
             "TRACE" => LevelFilter::Trace,
-            _ => return Err(py_err("Unknown log level: {level:?}")),
+            _ => {
+                return Err(py_err::<ConfigError>(format!(
+                    "Unknown log level: {level:?}"
+                )))
+            }
         }
     } else {
         LevelFilter::Info
@@ -37,7 +44,9 @@ pub fn log_level(py: Python, level: Option<String>) -> PyResult<()> {
         .install()
     {
         if !err.to_string().contains("already initialized") {
-            return Err(py_err(format!("Failed to subscribe logger, {err}")));
+            return Err(py_err::<ConfigError>(format!(
+                "Failed to subscribe logger, {err}"
+            )));
         }
     }
     log::set_max_level(tracing_level);
@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write,
+    net::SocketAddr,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{
+    body::Incoming,
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Method,
+    Request,
+    Response,
+    StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use pyo3::{
+    pyfunction,
+    PyResult,
+    Python,
+};
+use tokio::net::TcpListener;
+use tracing::{
+    debug,
+    info,
+};
+
+use crate::{
+    events::{
+        session_monitors,
+        SessionState,
+    },
+    listener::listener_stats,
+    py_err,
+    upstream::ListenerMetrics,
+    ConfigError,
+};
+
+// how long to wait before accepting again after an accept error
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+const CONTENT_TYPE_OPENMETRICS: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Serve OpenMetrics text describing every session and listener at ``/metrics`` on the
+/// given address, for scraping by Prometheus. The server runs in the background for the
+/// life of the process.
+///
+/// :param str addr: The address to listen on, defaults to "127.0.0.1:9464". Use port 0 to pick a free port.
+/// :return: The address the server is listening on.
+/// :rtype: str
+#[pyfunction]
+#[pyo3(signature = (addr="127.0.0.1:9464"))]
+pub(crate) fn serve_metrics(py: Python, addr: &str) -> PyResult<String> {
+    let addr: SocketAddr = addr.parse().map_err(|e| {
+        py_err::<ConfigError>(format!("Cannot parse metrics address: {addr}, error: {e}"))
+    })?;
+    let listener = py.allow_threads(|| std::net::TcpListener::bind(addr))?;
+    listener.set_nonblocking(true)?;
+    let local_addr = listener.local_addr()?;
+
+    let runtime = pyo3_async_runtimes::tokio::get_runtime();
+    let _guard = runtime.enter();
+    let listener = TcpListener::from_std(listener)?;
+    runtime.spawn(serve(listener));
+    info!("Serving metrics on {local_addr}");
+    Ok(local_addr.to_string())
+}
+
+async fn serve(listener: TcpListener) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                debug!("Error accepting metrics connection: {e:?}");
+                // back off rather than spin, such as when out of file descriptors
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service_fn(handle))
+                .await
+            {
+                debug!("Error serving metrics to {peer}: {e:?}");
+            }
+        });
+    }
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let mut resp = Response::builder();
+    let body = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            resp = resp.header(CONTENT_TYPE, CONTENT_TYPE_OPENMETRICS);
+            render().await
+        }
+        _ => {
+            resp = resp.status(StatusCode::NOT_FOUND);
+            "not found\n".to_string()
+        }
+    };
+    Ok(resp.body(Full::new(Bytes::from(body))).unwrap_or_default())
+}
+
+/// Render the current metrics in the OpenMetrics text format.
+async fn render() -> String {
+    let mut sessions = session_monitors();
+    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut listeners = listener_stats().await;
+    listeners.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "ngrok_session_up",
+        "gauge",
+        "Whether the session is connected to the ngrok service.",
+        None,
+    );
+    for (id, monitor) in &sessions {
+        let up = u8::from(monitor.state() == SessionState::Connected);
+        sample(&mut out, "ngrok_session_up", &[("session_id", id)], up);
+    }
+
+    family(
+        &mut out,
+        "ngrok_session_reconnects",
+        "counter",
+        "Reconnect attempts made by the session.",
+        None,
+    );
+    for (id, monitor) in &sessions {
+        sample(
+            &mut out,
+            "ngrok_session_reconnects_total",
+            &[("session_id", id)],
+            monitor.reconnects(),
+        );
+    }
+
+    family(
+        &mut out,
+        "ngrok_session_heartbeat_latency_seconds",
+        "gauge",
+        "Latency of the session's most recent heartbeat.",
+        Some("seconds"),
+    );
+    for (id, monitor) in &sessions {
+        if let Some(latency) = monitor.heartbeat_latency() {
+            sample(
+                &mut out,
+                "ngrok_session_heartbeat_latency_seconds",
+                &[("session_id", id)],
+                latency.as_secs_f64(),
+            );
+        }
+    }
+
+    let mut by_proto: BTreeMap<&str, u64> = BTreeMap::new();
+    for listener in &listeners {
+        *by_proto.entry(listener.proto.as_str()).or_default() += 1;
+    }
+    family(
+        &mut out,
+        "ngrok_listeners",
+        "gauge",
+        "Running listeners by protocol.",
+        None,
+    );
+    for (proto, count) in by_proto {
+        sample(&mut out, "ngrok_listeners", &[("proto", proto)], count);
+    }
+
+    type Getter = fn(&ListenerMetrics) -> u64;
+    let families: [(&str, &str, &str, Option<&str>, Getter); 5] = [
+        (
+            "ngrok_listener_connections",
+            "counter",
+            "Connections accepted by the listener.",
+            None,
+            ListenerMetrics::accepted_count,
+        ),
+        (
+            "ngrok_listener_active_connections",
+            "gauge",
+            "Connections currently being forwarded by the listener.",
+            None,
+            ListenerMetrics::active_count,
+        ),
+        (
+            "ngrok_listener_received_bytes",
+            "counter",
+            "Bytes received from the ngrok edge.",
+            Some("bytes"),
+            ListenerMetrics::bytes_in,
+        ),
+        (
+            "ngrok_listener_sent_bytes",
+            "counter",
+            "Bytes sent to the ngrok edge.",
+            Some("bytes"),
+            ListenerMetrics::bytes_out,
+        ),
+        (
+            "ngrok_listener_forward_errors",
+            "counter",
+            "Connections which could not be forwarded upstream.",
+            None,
+            ListenerMetrics::error_count,
+        ),
+    ];
+    for (name, kind, help, unit, get) in families {
+        family(&mut out, name, kind, help, unit);
+        // counter samples carry a suffix on the family name
+        let sample_name = if kind == "counter" {
+            format!("{name}_total")
+        } else {
+            name.to_string()
+        };
+        for listener in &listeners {
+            let labels = [
+                ("listener_id", listener.id.as_str()),
+                ("session_id", &listener.session_id),
+                ("proto", &listener.proto),
+            ];
+            sample(&mut out, &sample_name, &labels, get(&listener.metrics));
+        }
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str, unit: Option<&str>) {
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    if let Some(unit) = unit {
+        let _ = writeln!(out, "# UNIT {name} {unit}");
+    }
+    let _ = writeln!(out, "# HELP {name} {help}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect();
+    let _ = writeln!(out, "{name}{{{}}} {value}", labels.join(","));
+}
+
+/// Escape a label value per the OpenMetrics text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
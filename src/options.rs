@@ -0,0 +1,408 @@
+use pyo3::{
+    pyclass,
+    pymethods,
+    types::{
+        PyAnyMethods,
+        PyDict,
+        PyModule,
+    },
+    Bound,
+    IntoPy,
+    PyAny,
+    PyObject,
+    PyResult,
+    Python,
+};
+
+use crate::{
+    connect::{
+        configure_http,
+        configure_labeled,
+        configure_tcp,
+        configure_tls,
+        did_you_mean,
+        get_bool,
+        get_float,
+        get_list,
+        get_pem,
+        get_policy,
+        get_string,
+    },
+    listener_builder::{
+        HttpListenerBuilder,
+        LabeledListenerBuilder,
+        TcpListenerBuilder,
+        TlsListenerBuilder,
+    },
+    py_err,
+    ConfigError,
+};
+
+/// A typed options field, as found in a :meth:`forward` dict.
+trait OptionField: Sized {
+    /// Read the field from a dict value, coercing it the way :meth:`forward` does.
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self>;
+    /// The dict value for the field, or None if it is unset.
+    fn to_value(&self, py: Python) -> Option<PyObject>;
+}
+
+impl OptionField for Option<String> {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_string(key, v).map(Some)
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.as_ref().map(|v| v.into_py(py))
+    }
+}
+
+impl OptionField for Option<bool> {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_bool(key, v).map(Some)
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.map(|v| v.into_py(py))
+    }
+}
+
+impl OptionField for Option<f64> {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_float(key, v).map(Some)
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.map(|v| v.into_py(py))
+    }
+}
+
+impl OptionField for Vec<String> {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_list(v)?
+            .into_iter()
+            .map(|v| get_string(key, v))
+            .collect()
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        (!self.is_empty()).then(|| self.clone().into_py(py))
+    }
+}
+
+/// Read certificate material as PEM text, so the options stay JSON friendly.
+fn pem_text(key: &str, v: &PyAny) -> PyResult<String> {
+    String::from_utf8(get_pem(key, v)?.to_vec())
+        .map_err(|_| py_err::<ConfigError>(format!("option {key:?} must be PEM text")))
+}
+
+/// A single piece of certificate material, such as a key.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Pem(Option<String>);
+
+impl OptionField for Pem {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        pem_text(key, v).map(|pem| Pem(Some(pem)))
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.0.to_value(py)
+    }
+}
+
+/// A list of certificates, such as CAs.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct PemList(Vec<String>);
+
+impl OptionField for PemList {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_list(v)?
+            .into_iter()
+            .map(|v| pem_text(key, v))
+            .collect::<PyResult<_>>()
+            .map(PemList)
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.0.to_value(py)
+    }
+}
+
+/// A traffic policy, given as text or a :class:`TrafficPolicy` and kept as text.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Policy(Option<String>);
+
+impl OptionField for Policy {
+    fn extract(key: &str, v: &PyAny) -> PyResult<Self> {
+        get_policy(key, v).map(|policy| Policy(Some(policy.0)))
+    }
+    fn to_value(&self, py: Python) -> Option<PyObject> {
+        self.0.to_value(py)
+    }
+}
+
+/// Define an options pyclass, with a typed field for each :meth:`forward` option of a proto.
+macro_rules! make_options {
+    (
+        $(#[$outer:meta])*
+        $name:ident, $proto:literal, $builder:ty, $configure:ident,
+        aliases: {$($alias:literal => $target:literal),* $(,)?},
+        fields: {$($(#[$field_meta:meta])* $field:ident: $ty:ty),* $(,)?}
+    ) => {
+        $(#[$outer])*
+        #[pyclass(eq)]
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub(crate) struct $name {
+            $($(#[$field_meta])* $field: $ty,)*
+        }
+
+        impl $name {
+            const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+            const ALIASES: &'static [(&'static str, &'static str)] = &[$(($alias, $target)),*];
+
+            fn dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+                let dict = PyDict::new(py);
+                dict.set_item("proto", $proto)?;
+                $(
+                    if let Some(v) = OptionField::to_value(&self.$field, py) {
+                        dict.set_item(stringify!($field), v)?;
+                    }
+                )*
+                Ok(dict)
+            }
+        }
+
+        #[pymethods]
+        impl $name {
+            #[new]
+            #[pyo3(signature = (**options))]
+            fn new(options: Option<&PyDict>) -> PyResult<Self> {
+                match options {
+                    Some(options) => Self::from_dict(options),
+                    None => Ok(Self::default()),
+                }
+            }
+
+            /// Create the options from a dict with the keys used by :meth:`forward`.
+            /// Unknown keys, and keys which don't apply to this proto, raise a :class:`ConfigError`.
+            #[staticmethod]
+            pub fn from_dict(options: &PyDict) -> PyResult<Self> {
+                let mut keys = vec![];
+                for (k, v) in options.iter() {
+                    if !v.is_none() {
+                        keys.push((k.extract::<String>()?.replace('.', "_"), v));
+                    }
+                }
+                let mut problems = vec![];
+                let mut fields = vec![];
+                for (key, v) in keys {
+                    let key = Self::ALIASES
+                        .iter()
+                        .find(|(alias, _)| *alias == key)
+                        .map(|(_, target)| target.to_string())
+                        .unwrap_or(key);
+                    if key == "proto" {
+                        let proto = get_string("proto", v)?;
+                        if proto != $proto {
+                            problems.push(format!("proto {proto:?} does not match {:?}", $proto));
+                        }
+                    } else if Self::FIELDS.contains(&key.as_str()) {
+                        fields.push((key, v));
+                    } else {
+                        match did_you_mean(&key, Self::FIELDS.iter().copied()) {
+                            Some(s) => problems.push(format!("unknown option {key:?}, did you mean {s:?}?")),
+                            None => problems.push(format!("unknown option {key:?} for proto {:?}", $proto)),
+                        }
+                    }
+                }
+                if !problems.is_empty() {
+                    return Err(py_err::<ConfigError>(problems.join("; ")));
+                }
+
+                let mut opts = Self::default();
+                for (key, v) in fields {
+                    match key.as_str() {
+                        $(stringify!($field) => opts.$field = OptionField::extract(&key, v)?,)*
+                        _ => unreachable!("option keys are checked above"),
+                    }
+                }
+                Ok(opts)
+            }
+
+            /// Create the options from a JSON object with the keys used by :meth:`forward`.
+            #[staticmethod]
+            pub fn from_json(py: Python, json: &str) -> PyResult<Self> {
+                let value = PyModule::import_bound(py, "json")?.call_method1("loads", (json,))?;
+                let dict = value
+                    .downcast::<PyDict>()
+                    .map_err(|_| py_err::<ConfigError>("options JSON must be an object"))?;
+                Self::from_dict(dict.as_gil_ref())
+            }
+
+            /// The options as a dict which can be passed to :meth:`forward` as keyword arguments.
+            /// Unset options are left out, and "proto" is always set.
+            pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+                self.dict(py)
+            }
+
+            /// The options as a JSON object, with sorted keys so that configs can be diffed.
+            pub fn to_json(&self, py: Python) -> PyResult<String> {
+                let kwargs = PyDict::new_bound(py);
+                kwargs.set_item("sort_keys", true)?;
+                kwargs.set_item("indent", 2)?;
+                PyModule::import_bound(py, "json")?
+                    .call_method("dumps", (self.dict(py)?,), Some(&kwargs))?
+                    .extract()
+            }
+
+            /// Apply the options to a listener builder, returning the builder.
+            pub fn apply<'py>(
+                &self,
+                py: Python<'py>,
+                builder: Bound<'py, $builder>,
+            ) -> PyResult<Bound<'py, $builder>> {
+                $configure(&builder, self.dict(py)?)?;
+                Ok(builder)
+            }
+
+            fn __repr__(&self, py: Python) -> PyResult<String> {
+                let dict = self.dict(py)?;
+                dict.del_item("proto")?;
+                let mut parts = vec![];
+                for (k, v) in dict.iter() {
+                    parts.push(format!("{}={}", k.extract::<String>()?, v.repr()?));
+                }
+                Ok(format!("{}({})", stringify!($name), parts.join(", ")))
+            }
+        }
+    };
+}
+
+make_options!(
+    /// Typed options for an HTTP endpoint, with the same names as the options of :meth:`forward`.
+    HttpOptions, "http", HttpListenerBuilder, configure_http,
+    aliases: {"hostname" => "domain", "policy" => "traffic_policy"},
+    fields: {
+        #[pyo3(get, set)] addr: Option<String>,
+        #[pyo3(get, set)] metadata: Option<String>,
+        #[pyo3(get, set)] allow_cidr: Vec<String>,
+        #[pyo3(get, set)] deny_cidr: Vec<String>,
+        #[pyo3(get, set)] proxy_proto: Option<String>,
+        #[pyo3(get, set)] forwards_to: Option<String>,
+        #[pyo3(get, set)] verify_upstream_tls: Option<bool>,
+        #[pyo3(get, set)] pooling_enabled: Option<bool>,
+        traffic_policy: Policy,
+        #[pyo3(get, set)] domain: Option<String>,
+        #[pyo3(get, set)] schemes: Vec<String>,
+        #[pyo3(get, set)] app_protocol: Option<String>,
+        mutual_tls_cas: PemList,
+        #[pyo3(get, set)] compression: Option<bool>,
+        #[pyo3(get, set)] websocket_tcp_converter: Option<bool>,
+        #[pyo3(get, set)] request_header_add: Vec<String>,
+        #[pyo3(get, set)] response_header_add: Vec<String>,
+        #[pyo3(get, set)] request_header_remove: Vec<String>,
+        #[pyo3(get, set)] response_header_remove: Vec<String>,
+        #[pyo3(get, set)] basic_auth: Vec<String>,
+        #[pyo3(get, set)] allow_user_agent: Vec<String>,
+        #[pyo3(get, set)] deny_user_agent: Vec<String>,
+        #[pyo3(get, set)] circuit_breaker: Option<f64>,
+        #[pyo3(get, set)] oauth_provider: Option<String>,
+        #[pyo3(get, set)] oauth_allow_emails: Vec<String>,
+        #[pyo3(get, set)] oauth_allow_domains: Vec<String>,
+        #[pyo3(get, set)] oauth_scopes: Vec<String>,
+        #[pyo3(get, set)] oauth_client_id: Option<String>,
+        #[pyo3(get, set)] oauth_client_secret: Option<String>,
+        #[pyo3(get, set)] oidc_issuer_url: Option<String>,
+        #[pyo3(get, set)] oidc_client_id: Option<String>,
+        #[pyo3(get, set)] oidc_client_secret: Option<String>,
+        #[pyo3(get, set)] oidc_allow_emails: Vec<String>,
+        #[pyo3(get, set)] oidc_allow_domains: Vec<String>,
+        #[pyo3(get, set)] oidc_scopes: Vec<String>,
+        #[pyo3(get, set)] verify_webhook_provider: Option<String>,
+        #[pyo3(get, set)] verify_webhook_secret: Option<String>,
+    }
+);
+
+make_options!(
+    /// Typed options for a TCP endpoint, with the same names as the options of :meth:`forward`.
+    TcpOptions, "tcp", TcpListenerBuilder, configure_tcp,
+    aliases: {"policy" => "traffic_policy"},
+    fields: {
+        #[pyo3(get, set)] addr: Option<String>,
+        #[pyo3(get, set)] metadata: Option<String>,
+        #[pyo3(get, set)] allow_cidr: Vec<String>,
+        #[pyo3(get, set)] deny_cidr: Vec<String>,
+        #[pyo3(get, set)] proxy_proto: Option<String>,
+        #[pyo3(get, set)] forwards_to: Option<String>,
+        #[pyo3(get, set)] verify_upstream_tls: Option<bool>,
+        #[pyo3(get, set)] pooling_enabled: Option<bool>,
+        traffic_policy: Policy,
+        #[pyo3(get, set)] remote_addr: Option<String>,
+    }
+);
+
+make_options!(
+    /// Typed options for a TLS endpoint, with the same names as the options of :meth:`forward`.
+    TlsOptions, "tls", TlsListenerBuilder, configure_tls,
+    aliases: {"hostname" => "domain", "policy" => "traffic_policy"},
+    fields: {
+        #[pyo3(get, set)] addr: Option<String>,
+        #[pyo3(get, set)] metadata: Option<String>,
+        #[pyo3(get, set)] allow_cidr: Vec<String>,
+        #[pyo3(get, set)] deny_cidr: Vec<String>,
+        #[pyo3(get, set)] proxy_proto: Option<String>,
+        #[pyo3(get, set)] forwards_to: Option<String>,
+        #[pyo3(get, set)] verify_upstream_tls: Option<bool>,
+        #[pyo3(get, set)] pooling_enabled: Option<bool>,
+        traffic_policy: Policy,
+        #[pyo3(get, set)] domain: Option<String>,
+        mutual_tls_cas: PemList,
+        crt: Pem,
+        key: Pem,
+    }
+);
+
+make_options!(
+    /// Typed options for a labeled listener, with the same names as the options of :meth:`forward`.
+    LabeledOptions, "labeled", LabeledListenerBuilder, configure_labeled,
+    aliases: {},
+    fields: {
+        #[pyo3(get, set)] addr: Option<String>,
+        #[pyo3(get, set)] metadata: Option<String>,
+        #[pyo3(get, set)] app_protocol: Option<String>,
+        #[pyo3(get, set)] verify_upstream_tls: Option<bool>,
+        #[pyo3(get, set)] labels: Vec<String>,
+    }
+);
+
+/// Getters and setters for the certificate and policy fields, which accept the same values
+/// as :meth:`forward`.
+macro_rules! field_accessors {
+    ($name:ident, $($field:ident, $setter:ident, $ty:ty);* $(;)?) => {
+        #[pymethods]
+        impl $name {
+            $(
+                #[getter]
+                fn $field(&self, py: Python) -> PyObject {
+                    self.$field.0.clone().into_py(py)
+                }
+
+                #[setter]
+                fn $setter(&mut self, value: Option<&PyAny>) -> PyResult<()> {
+                    self.$field = match value {
+                        Some(v) if !v.is_none() => <$ty>::extract(stringify!($field), v)?,
+                        _ => <$ty>::default(),
+                    };
+                    Ok(())
+                }
+            )*
+        }
+    };
+}
+
+field_accessors!(
+    HttpOptions,
+    traffic_policy, set_traffic_policy, Policy;
+    mutual_tls_cas, set_mutual_tls_cas, PemList;
+);
+field_accessors!(TcpOptions, traffic_policy, set_traffic_policy, Policy);
+field_accessors!(
+    TlsOptions,
+    traffic_policy, set_traffic_policy, Policy;
+    mutual_tls_cas, set_mutual_tls_cas, PemList;
+    crt, set_crt, Pem;
+    key, set_key, Pem;
+);
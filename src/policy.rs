@@ -0,0 +1,553 @@
+use std::fmt;
+
+use pyo3::{
+    pyclass,
+    pymethods,
+    types::{
+        PyAnyMethods,
+        PyModule,
+    },
+    Bound,
+    FromPyObject,
+    Py,
+    PyAny,
+    PyErr,
+    PyRef,
+    PyResult,
+    Python,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+use tracing::warn;
+
+use crate::{
+    connect::did_you_mean,
+    listener_builder::REDACTED,
+    py_err,
+    ConfigError,
+};
+
+/// Which phases an action may be used in.
+#[derive(Clone, Copy, PartialEq)]
+enum Phases {
+    Http,
+    Tcp,
+    Any,
+}
+
+/// The known actions, with the config fields each one requires. Other action types are
+/// passed on to the ngrok service unchecked, so new ones can be used before they are
+/// listed here.
+const ACTIONS: &[(&str, Phases, &[&str])] = &[
+    ("add-headers", Phases::Http, &["headers"]),
+    ("basic-auth", Phases::Http, &["credentials"]),
+    // error_threshold defaults to 0.5
+    ("circuit-breaker", Phases::Http, &[]),
+    ("close-connection", Phases::Any, &[]),
+    ("compress-response", Phases::Http, &[]),
+    ("custom-response", Phases::Http, &["status_code"]),
+    ("deny", Phases::Any, &[]),
+    ("forward-internal", Phases::Any, &["url"]),
+    ("http-request", Phases::Http, &["url"]),
+    ("jwt-validation", Phases::Http, &["issuer", "audience"]),
+    ("log", Phases::Any, &[]),
+    ("oauth", Phases::Http, &["provider"]),
+    (
+        "openid-connect",
+        Phases::Http,
+        &["issuer_url", "client_id", "client_secret"],
+    ),
+    (
+        "rate-limit",
+        Phases::Http,
+        &["algorithm", "capacity", "rate", "bucket_key"],
+    ),
+    ("redirect", Phases::Http, &["to"]),
+    ("remove-headers", Phases::Http, &["headers"]),
+    ("restrict-ips", Phases::Any, &[]),
+    ("set-vars", Phases::Any, &["vars"]),
+    ("terminate-tls", Phases::Tcp, &[]),
+    ("url-rewrite", Phases::Http, &["to"]),
+    ("verify-webhook", Phases::Http, &["provider", "secret"]),
+];
+
+/// The config fields of actions which hold secrets, redacted from a builder's config().
+const SECRETS: &[(&str, &str)] = &[
+    ("basic-auth", "credentials"),
+    ("oauth", "client_secret"),
+    ("openid-connect", "client_secret"),
+    ("verify-webhook", "secret"),
+];
+
+/// The serialized form of a traffic policy.
+// unknown fields are kept rather than rejected, so that policies using fields added to
+// the service since this was written still load and are passed on whole
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Policy {
+    // inbound and outbound are the older names of the http phases
+    #[serde(default, alias = "inbound", skip_serializing_if = "Vec::is_empty")]
+    on_http_request: Vec<Rule>,
+    #[serde(default, alias = "outbound", skip_serializing_if = "Vec::is_empty")]
+    on_http_response: Vec<Rule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    on_tcp_connect: Vec<Rule>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Rule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    expressions: Vec<String>,
+    actions: Vec<Action>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, try_from = "RawAction")]
+struct Action {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<Value>,
+}
+
+/// An action as written, before it is checked against the known actions.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAction {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    config: Option<Value>,
+}
+
+/// A problem with a policy, which deserialization places at a line and column.
+#[derive(Debug)]
+struct PolicyError(String);
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<RawAction> for Action {
+    type Error = PolicyError;
+
+    fn try_from(raw: RawAction) -> Result<Self, Self::Error> {
+        let kind = raw.kind;
+        let required = match ACTIONS.iter().find(|(name, _, _)| *name == kind) {
+            Some((_, _, required)) => *required,
+            None => {
+                let names = ACTIONS.iter().map(|(name, _, _)| *name);
+                match did_you_mean(&kind, names) {
+                    Some(s) => warn!("Unknown action type {kind:?}, did you mean {s:?}?"),
+                    None => warn!("Unknown action type {kind:?}, passing it on unchecked"),
+                }
+                &[]
+            }
+        };
+        let missing: Vec<&str> = required
+            .iter()
+            .copied()
+            .filter(|field| {
+                raw.config
+                    .as_ref()
+                    .and_then(|c| c.get(field))
+                    .filter(|v| !v.is_null())
+                    .is_none()
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(PolicyError(format!(
+                "action {kind:?} is missing required config: {}",
+                missing.join(", ")
+            )));
+        }
+        if raw.config.as_ref().is_some_and(|c| !c.is_object()) {
+            return Err(PolicyError(format!(
+                "config of action {kind:?} must be a mapping"
+            )));
+        }
+        Ok(Action {
+            kind,
+            config: raw.config,
+        })
+    }
+}
+
+impl Policy {
+    /// Check that each action is used in a phase it applies to.
+    fn validate(&self) -> Result<(), PolicyError> {
+        for (phase, rules, kind) in [
+            ("on_http_request", &self.on_http_request, Phases::Http),
+            ("on_http_response", &self.on_http_response, Phases::Http),
+            ("on_tcp_connect", &self.on_tcp_connect, Phases::Tcp),
+        ] {
+            for (i, rule) in rules.iter().enumerate() {
+                if rule.actions.is_empty() {
+                    return Err(PolicyError(format!("{phase}[{i}]: rule has no actions")));
+                }
+                for (j, action) in rule.actions.iter().enumerate() {
+                    // actions which aren't known are left for the service to check
+                    let misplaced = ACTIONS.iter().any(|(name, phases, _)| {
+                        *name == action.kind && *phases != Phases::Any && *phases != kind
+                    });
+                    if misplaced {
+                        return Err(PolicyError(format!(
+                            "{phase}[{i}].actions[{j}]: action {:?} cannot be used in {phase}",
+                            action.kind
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A ConfigError for a policy problem, with ``line`` and ``column`` attributes when known.
+fn policy_err(source: &str, message: impl fmt::Display, location: Option<(usize, usize)>) -> PyErr {
+    let err = py_err::<ConfigError>(format!("invalid traffic policy {source}: {message}"));
+    Python::with_gil(|py| {
+        let value = err.value_bound(py);
+        let (line, column) = location.unzip();
+        let _ = value.setattr("line", line);
+        let _ = value.setattr("column", column);
+    });
+    err
+}
+
+fn from_json_str(source: &str, json: &str) -> PyResult<Policy> {
+    let policy: Policy = serde_json::from_str(json)
+        .map_err(|e| policy_err(source, &e, Some((e.line(), e.column()))))?;
+    policy.validate().map_err(|e| policy_err(source, e, None))?;
+    Ok(policy)
+}
+
+fn from_yaml_str(source: &str, yaml: &str) -> PyResult<Policy> {
+    let policy: Policy = serde_yaml::from_str(yaml).map_err(|e| {
+        let location = e.location().map(|l| (l.line(), l.column()));
+        policy_err(source, &e, location)
+    })?;
+    policy.validate().map_err(|e| policy_err(source, e, None))?;
+    Ok(policy)
+}
+
+/// A traffic policy with the secrets in its actions' config redacted, as JSON text.
+/// Text which can't be parsed as JSON or YAML is redacted entirely.
+pub(crate) fn redact(text: &str) -> String {
+    let parsed = serde_json::from_str::<Value>(text)
+        .or_else(|_| serde_yaml::from_str::<Value>(text).map_err(|_| ()));
+    let Ok(mut policy) = parsed else {
+        return REDACTED.to_string();
+    };
+    let actions = policy
+        .as_object_mut()
+        .into_iter()
+        .flat_map(|phases| phases.values_mut())
+        .filter_map(Value::as_array_mut)
+        .flatten()
+        .filter_map(|rule| rule.get_mut("actions").and_then(Value::as_array_mut))
+        .flatten();
+    for action in actions {
+        let kind = action
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let fields: Vec<&str> = SECRETS
+            .iter()
+            .filter(|(name, _)| *name == kind)
+            .map(|(_, field)| *field)
+            .collect();
+        let Some(config) = action.get_mut("config").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        for field in fields {
+            match config.get_mut(field) {
+                Some(Value::Array(credentials)) if field == "credentials" => {
+                    credentials.iter_mut().for_each(redact_credential)
+                }
+                Some(value) => *value = REDACTED.into(),
+                None => {}
+            }
+        }
+    }
+    policy.to_string()
+}
+
+/// Redact a "user:password" credential, keeping the user name as basic_auth does.
+fn redact_credential(credential: &mut Value) {
+    let user = credential
+        .as_str()
+        .and_then(|c| c.split_once(':'))
+        .map(|(user, _)| user.to_string());
+    *credential = match user {
+        Some(user) => format!("{user}:{REDACTED}").into(),
+        None => REDACTED.into(),
+    };
+}
+
+/// Convert a Python value to JSON by way of the json module.
+fn to_value(py: Python, obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let json: String = PyModule::import_bound(py, "json")?
+        .call_method1("dumps", (obj,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|e| py_err::<ConfigError>(e.to_string()))
+}
+
+/// Convert JSON to a Python value by way of the json module.
+fn from_value(py: Python, value: &impl Serialize) -> PyResult<Py<PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| py_err::<ConfigError>(e.to_string()))?;
+    Ok(PyModule::import_bound(py, "json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
+
+/// An action taken by a :class:`PolicyRule`, such as "custom-response" or "deny".
+/// The required config fields of known action types are checked when it is created,
+/// while other types log a warning and are left for the ngrok service to check.
+///
+/// :param str type: The type of the action.
+/// :param dict or None config: The configuration of the action.
+#[pyclass(eq)]
+#[derive(Clone, PartialEq)]
+pub(crate) struct PolicyAction {
+    action: Action,
+}
+
+#[pymethods]
+impl PolicyAction {
+    #[new]
+    #[pyo3(signature = (r#type, config=None))]
+    fn new(py: Python, r#type: String, config: Option<Bound<'_, PyAny>>) -> PyResult<Self> {
+        let config = config
+            .filter(|c| !c.is_none())
+            .map(|c| to_value(py, &c))
+            .transpose()?;
+        let action = Action::try_from(RawAction {
+            kind: r#type,
+            config,
+        })
+        .map_err(|e| policy_err("action", e, None))?;
+        Ok(PolicyAction { action })
+    }
+
+    /// The type of the action.
+    #[getter(r#type)]
+    fn kind(&self) -> String {
+        self.action.kind.clone()
+    }
+
+    /// The configuration of the action, or None.
+    #[getter]
+    fn config(&self, py: Python) -> PyResult<Py<PyAny>> {
+        from_value(py, &self.action.config)
+    }
+}
+
+/// A rule of a :class:`TrafficPolicy`, which takes its actions when all of its
+/// expressions match.
+///
+/// :param list actions: The :class:`PolicyAction` list to take.
+/// :param list or None expressions: CEL expressions which must all match, the rule always applies if none are given.
+/// :param str or None name: The name of the rule.
+#[pyclass(eq)]
+#[derive(Clone, PartialEq)]
+pub(crate) struct PolicyRule {
+    rule: Rule,
+}
+
+#[pymethods]
+impl PolicyRule {
+    #[new]
+    #[pyo3(signature = (actions, expressions=None, name=None))]
+    fn new(
+        actions: Vec<PyRef<PolicyAction>>,
+        expressions: Option<Vec<String>>,
+        name: Option<String>,
+    ) -> Self {
+        PolicyRule {
+            rule: Rule {
+                name,
+                expressions: expressions.unwrap_or_default(),
+                actions: actions.iter().map(|a| a.action.clone()).collect(),
+                other: Map::new(),
+            },
+        }
+    }
+
+    /// The name of the rule, or None.
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.rule.name.clone()
+    }
+
+    /// The expressions which must all match for the rule to apply.
+    #[getter]
+    fn expressions(&self) -> Vec<String> {
+        self.rule.expressions.clone()
+    }
+
+    /// The actions the rule takes.
+    #[getter]
+    fn actions(&self) -> Vec<PolicyAction> {
+        self.rule
+            .actions
+            .iter()
+            .map(|action| PolicyAction {
+                action: action.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A Traffic Policy, made of :class:`PolicyRule` lists for each phase. It is validated
+/// locally when created or loaded, and can be passed to ``traffic_policy()`` on a listener
+/// builder, to :meth:`forward` or to the typed options such as :class:`HttpOptions`.
+/// See `Traffic Policy`_ in the ngrok docs for additional details.
+///
+/// .. _Traffic Policy: https://ngrok.com/docs/traffic-policy/
+///
+/// :param list or None on_http_request: Rules for HTTP requests.
+/// :param list or None on_http_response: Rules for HTTP responses.
+/// :param list or None on_tcp_connect: Rules for TCP connections.
+#[pyclass(eq)]
+#[derive(Clone, PartialEq)]
+pub(crate) struct TrafficPolicy {
+    policy: Policy,
+}
+
+fn rules(rules: Option<Vec<PyRef<PolicyRule>>>) -> Vec<Rule> {
+    rules
+        .unwrap_or_default()
+        .iter()
+        .map(|r| r.rule.clone())
+        .collect()
+}
+
+#[pymethods]
+impl TrafficPolicy {
+    #[new]
+    #[pyo3(signature = (on_http_request=None, on_http_response=None, on_tcp_connect=None))]
+    fn new(
+        on_http_request: Option<Vec<PyRef<PolicyRule>>>,
+        on_http_response: Option<Vec<PyRef<PolicyRule>>>,
+        on_tcp_connect: Option<Vec<PyRef<PolicyRule>>>,
+    ) -> PyResult<Self> {
+        let policy = Policy {
+            on_http_request: rules(on_http_request),
+            on_http_response: rules(on_http_response),
+            on_tcp_connect: rules(on_tcp_connect),
+            other: Map::new(),
+        };
+        policy
+            .validate()
+            .map_err(|e| policy_err("policy", e, None))?;
+        Ok(TrafficPolicy { policy })
+    }
+
+    /// Parse a policy from JSON text.
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        from_json_str("JSON", json).map(|policy| TrafficPolicy { policy })
+    }
+
+    /// Parse a policy from YAML text.
+    #[staticmethod]
+    pub fn from_yaml(yaml: &str) -> PyResult<Self> {
+        from_yaml_str("YAML", yaml).map(|policy| TrafficPolicy { policy })
+    }
+
+    /// Create a policy from a dict with the structure of the policy document.
+    #[staticmethod]
+    pub fn from_dict(py: Python, policy: Bound<'_, PyAny>) -> PyResult<Self> {
+        let json = to_value(py, &policy)?.to_string();
+        from_json_str("dict", &json).map(|policy| TrafficPolicy { policy })
+    }
+
+    /// Load a policy from a YAML or JSON file. Errors name the file, and have ``line``
+    /// and ``column`` attributes when the problem can be placed in it.
+    ///
+    /// :param str path: The path of the policy file, parsed as JSON if it ends in ".json" and YAML otherwise.
+    #[staticmethod]
+    pub fn load(py: Python, path: String) -> PyResult<Self> {
+        let text = py
+            .allow_threads(|| std::fs::read_to_string(&path))
+            .map_err(|e| {
+                py_err::<ConfigError>(format!("Cannot read traffic policy {path}: {e}"))
+            })?;
+        let policy = if path.ends_with(".json") {
+            from_json_str(&path, &text)?
+        } else {
+            from_yaml_str(&path, &text)?
+        };
+        Ok(TrafficPolicy { policy })
+    }
+
+    /// The rules for HTTP requests.
+    #[getter]
+    fn on_http_request(&self) -> Vec<PolicyRule> {
+        wrap_rules(&self.policy.on_http_request)
+    }
+
+    /// The rules for HTTP responses.
+    #[getter]
+    fn on_http_response(&self) -> Vec<PolicyRule> {
+        wrap_rules(&self.policy.on_http_response)
+    }
+
+    /// The rules for TCP connections.
+    #[getter]
+    fn on_tcp_connect(&self) -> Vec<PolicyRule> {
+        wrap_rules(&self.policy.on_tcp_connect)
+    }
+
+    /// The policy as a dict with the structure of the policy document.
+    pub fn to_dict(&self, py: Python) -> PyResult<Py<PyAny>> {
+        from_value(py, &self.policy)
+    }
+
+    /// The policy as JSON text.
+    pub fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.policy).map_err(|e| py_err::<ConfigError>(e.to_string()))
+    }
+
+    /// The policy as YAML text.
+    pub fn to_yaml(&self) -> PyResult<String> {
+        serde_yaml::to_string(&self.policy).map_err(|e| py_err::<ConfigError>(e.to_string()))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+}
+
+fn wrap_rules(rules: &[Rule]) -> Vec<PolicyRule> {
+    rules
+        .iter()
+        .map(|rule| PolicyRule { rule: rule.clone() })
+        .collect()
+}
+
+/// A traffic policy argument, given either as text or as a :class:`TrafficPolicy`.
+pub(crate) struct PolicyConfig(pub(crate) String);
+
+impl<'py> FromPyObject<'py> for PolicyConfig {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(policy) = ob.downcast::<TrafficPolicy>() {
+            return policy.borrow().to_json().map(PolicyConfig);
+        }
+        ob.extract::<String>().map(PolicyConfig)
+    }
+}
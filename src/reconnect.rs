@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use pyo3::PyResult;
+use rand::Rng;
+
+use crate::{
+    py_err,
+    ConfigError,
+};
+
+/// Limits on how a session reconnects to the ngrok service after a disconnection.
+#[derive(Clone, Debug)]
+pub(crate) struct ReconnectPolicy {
+    min_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    max_attempts: Option<u32>,
+    give_up_after: Option<Duration>,
+}
+
+impl ReconnectPolicy {
+    pub(crate) fn new(
+        min_backoff: f64,
+        max_backoff: f64,
+        multiplier: f64,
+        jitter: f64,
+        max_attempts: Option<u32>,
+        give_up_after: Option<f64>,
+    ) -> PyResult<Self> {
+        let min_backoff = seconds("min_backoff", min_backoff)?;
+        let max_backoff = seconds("max_backoff", max_backoff)?;
+        if min_backoff > max_backoff {
+            return Err(py_err::<ConfigError>(
+                "min_backoff must not be greater than max_backoff",
+            ));
+        }
+        if !multiplier.is_finite() || multiplier < 1.0 {
+            return Err(py_err::<ConfigError>(format!(
+                "multiplier must be at least 1.0, got {multiplier}"
+            )));
+        }
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(py_err::<ConfigError>(format!(
+                "jitter must be between 0.0 and 1.0, got {jitter}"
+            )));
+        }
+        Ok(ReconnectPolicy {
+            min_backoff,
+            max_backoff,
+            multiplier,
+            jitter,
+            max_attempts,
+            give_up_after: give_up_after
+                .map(|s| seconds("give_up_after", s))
+                .transpose()?,
+        })
+    }
+
+    /// The delay before the given reconnect attempt, starting at 1.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let exp = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let backoff = (self.min_backoff.as_secs_f64() * self.multiplier.powi(exp))
+            .min(self.max_backoff.as_secs_f64());
+        let spread = backoff * self.jitter;
+        let jittered = if spread > 0.0 {
+            backoff + rand::thread_rng().gen_range(-spread..=spread)
+        } else {
+            backoff
+        };
+        Duration::from_secs_f64(jittered.clamp(0.0, self.max_backoff.as_secs_f64()))
+    }
+
+    /// Why reconnecting should stop before the given attempt, if it should.
+    pub(crate) fn exhausted(&self, attempt: u32, outage: Duration) -> Option<String> {
+        if let Some(max) = self.max_attempts {
+            if attempt > max {
+                return Some(format!("gave up reconnecting after {max} attempts"));
+            }
+        }
+        if let Some(limit) = self.give_up_after {
+            if outage >= limit {
+                return Some(format!(
+                    "gave up reconnecting after {:.1}s",
+                    outage.as_secs_f64()
+                ));
+            }
+        }
+        None
+    }
+}
+
+fn seconds(name: &str, value: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(value).map_err(|_| {
+        py_err::<ConfigError>(format!(
+            "{name} must be a non-negative number of seconds, got {value}"
+        ))
+    })
+}
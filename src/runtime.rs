@@ -0,0 +1,148 @@
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex as SyncMutex;
+use pyo3::{
+    pyfunction,
+    types::{
+        PyDict,
+        PyDictMethods,
+    },
+    Bound,
+    PyResult,
+    Python,
+};
+use tokio::runtime::Builder;
+
+use crate::{
+    py_err,
+    ConfigError,
+};
+
+// tokio's defaults, for runtime_info
+const DEFAULT_MAX_BLOCKING_THREADS: usize = 512;
+const DEFAULT_THREAD_NAME: &str = "tokio-runtime-worker";
+
+// set by the first runtime thread to start, after which the builder is spent
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref CONFIG: SyncMutex<RuntimeConfig> = SyncMutex::new(RuntimeConfig::default());
+}
+
+/// The settings given to configure_runtime, with None for tokio's defaults.
+#[derive(Clone, Default)]
+struct RuntimeConfig {
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+    thread_name: Option<String>,
+}
+
+impl RuntimeConfig {
+    fn builder(&self) -> Builder {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all().on_thread_start(|| {
+            STARTED.store(true, Ordering::SeqCst);
+        });
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        builder
+    }
+}
+
+/// Install the default runtime builder, so the runtime's start can be detected.
+pub(crate) fn init() {
+    pyo3_async_runtimes::tokio::init(CONFIG.lock().builder());
+}
+
+/// Configure the tokio runtime which runs ngrok's sessions and listeners. The runtime starts
+/// with the first session, listener or call which needs it, so this must be called before
+/// then, typically right after import. Options left as None keep tokio's defaults.
+///
+/// :param int or None worker_threads: The number of worker threads, defaulting to one per CPU core.
+/// :param int or None max_blocking_threads: The most threads to run blocking work on, defaulting to 512.
+/// :param str or None thread_name: The name of the runtime's threads, defaulting to "tokio-runtime-worker".
+/// :param bool enable_io_uring: Use io_uring for file I/O. Not supported by this build, so must be False.
+#[pyfunction]
+#[pyo3(signature = (worker_threads=None, max_blocking_threads=None, thread_name=None, enable_io_uring=false))]
+pub fn configure_runtime(
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+    thread_name: Option<String>,
+    enable_io_uring: bool,
+) -> PyResult<()> {
+    if worker_threads == Some(0) {
+        return Err(py_err::<ConfigError>("worker_threads must be at least 1"));
+    }
+    if max_blocking_threads == Some(0) {
+        return Err(py_err::<ConfigError>(
+            "max_blocking_threads must be at least 1",
+        ));
+    }
+    if enable_io_uring {
+        return Err(py_err::<ConfigError>(
+            "io_uring is not supported by this build of ngrok",
+        ));
+    }
+
+    let mut config = CONFIG.lock();
+    if STARTED.load(Ordering::SeqCst) {
+        return Err(py_err::<ConfigError>(
+            "the ngrok runtime has already started, configure_runtime must be called before the first session connects",
+        ));
+    }
+    *config = RuntimeConfig {
+        worker_threads,
+        max_blocking_threads,
+        thread_name,
+    };
+    pyo3_async_runtimes::tokio::init(config.builder());
+    Ok(())
+}
+
+/// Describe the tokio runtime, as a dict with the keys "started", "worker_threads",
+/// "max_blocking_threads", "thread_name", "io_uring" and "alive_tasks", the number of
+/// tasks on the runtime or None before it starts. Before the runtime starts "worker_threads"
+/// is None unless it was configured.
+///
+/// :return: The runtime's settings and state.
+/// :rtype: dict
+#[pyfunction]
+pub fn runtime_info(py: Python) -> PyResult<Bound<PyDict>> {
+    let config = CONFIG.lock().clone();
+    let started = STARTED.load(Ordering::SeqCst);
+    let metrics = started.then(|| pyo3_async_runtimes::tokio::get_runtime().metrics());
+
+    let info = PyDict::new_bound(py);
+    info.set_item("started", started)?;
+    info.set_item(
+        "worker_threads",
+        metrics
+            .as_ref()
+            .map(|m| m.num_workers())
+            .or(config.worker_threads),
+    )?;
+    info.set_item(
+        "max_blocking_threads",
+        config
+            .max_blocking_threads
+            .unwrap_or(DEFAULT_MAX_BLOCKING_THREADS),
+    )?;
+    info.set_item(
+        "thread_name",
+        config.thread_name.as_deref().unwrap_or(DEFAULT_THREAD_NAME),
+    )?;
+    info.set_item("io_uring", false)?;
+    info.set_item("alive_tasks", metrics.as_ref().map(|m| m.num_alive_tasks()))?;
+    Ok(info)
+}
@@ -1,7 +1,11 @@
 use std::{
     borrow::BorrowMut,
     env,
-    sync::Arc,
+    future::Future,
+    sync::{
+        Arc,
+        Weak,
+    },
     time::Duration,
 };
 
@@ -21,28 +25,47 @@ use ngrok::{
 };
 use parking_lot::Mutex as SyncMutex;
 use pyo3::{
+    intern,
     pyclass,
     pyfunction,
     pymethods,
-    types::PyByteArray,
+    types::{
+        PyAnyMethods,
+        PyByteArray,
+        PyTuple,
+    },
     Bound,
+    IntoPy,
+    Py,
     PyAny,
     PyErr,
     PyObject,
+    PyRef,
     PyRefMut,
     PyResult,
     Python,
 };
+use pyo3_async_runtimes::TaskLocals;
 use rustls::ClientConfig;
 use tracing::{
     debug,
     info,
+    warn,
 };
 
 use crate::{
+    backend,
+    config::AgentConfig,
+    events::{
+        register_monitor,
+        SessionEvents,
+        SessionMonitor,
+        SessionState,
+    },
     listener::{
         list_listeners,
         remove_global_listener,
+        remove_session_listeners,
     },
     listener_builder::{
         HttpListenerBuilder,
@@ -50,17 +73,32 @@ use crate::{
         TcpListenerBuilder,
         TlsListenerBuilder,
     },
+    py_err,
     py_ngrok_err,
+    reconnect::ReconnectPolicy,
+    sync::SyncSession,
+    wrapper,
+    AuthError,
+    ConfigError,
+    ListenerError,
+    SessionError,
 };
 
 const CLIENT_TYPE: &str = "ngrok-python";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+// the upstream response to a command with no handler
+const NOT_IMPLEMENTED: &str = "the agent has not defined a callback for this operation";
 
 lazy_static! {
     // Allow user to store a default auth token to use for all sessions
     static ref AUTH_TOKEN: SyncMutex<Option<String>> = SyncMutex::new(None);
+    // Connected sessions which are still referenced, so they can all be closed on shutdown.
+    static ref LIVE_SESSIONS: SyncMutex<Vec<WeakSession>> = SyncMutex::new(vec![]);
 }
 
+/// A reference to a session which doesn't keep it alive.
+type WeakSession = (Weak<SyncMutex<NgrokSession>>, Weak<SessionMonitor>);
+
 /// Set the default auth token to use for any future sessions.
 #[pyfunction]
 #[allow(dead_code)]
@@ -75,6 +113,11 @@ pub fn set_auth_token(authtoken: String) {
 pub(crate) struct SessionBuilder {
     raw_builder: Arc<SyncMutex<NgrokSessionBuilder>>,
     disconnect_handler: Option<PyObject>,
+    stop_handler: Option<PyObject>,
+    restart_handler: Option<PyObject>,
+    update_handler: Option<PyObject>,
+    heartbeat_handler: Option<PyObject>,
+    reconnect_policy: Option<ReconnectPolicy>,
     auth_token_set: bool,
 }
 
@@ -105,48 +148,196 @@ impl SessionBuilder {
         f(&mut builder);
     }
 
-    /// Update the connector callback in the upstream rust sdk.
-    fn update_connector(&self) {
-        let disconnect_handler =
-            Python::with_gil(|py| self.disconnect_handler.as_ref().map(|h| h.clone_ref(py)));
-
-        self.set(|b| {
-            b.connector(
-                move |host: String,
-                      port: u16,
-                      tls_config: Arc<ClientConfig>,
-                      err: Option<AcceptError>| {
-                    let disconn_fn =
-                        Python::with_gil(|py| disconnect_handler.as_ref().map(|h| h.clone_ref(py)));
-                    async move {
+    /// Clone the upstream builder with the connector and handlers reporting to the
+    /// given session monitor, in addition to calling any python handlers.
+    fn build(&self, monitor: &Arc<SessionMonitor>) -> NgrokSessionBuilder {
+        let mut builder = self.raw_builder.lock().clone();
+
+        // coroutine handlers are run on the event loop which connected the session
+        let locals = Python::with_gil(|py| pyo3_async_runtimes::tokio::get_current_locals(py).ok());
+        let callback = |handler: &Option<PyObject>| {
+            Python::with_gil(|py| {
+                handler.as_ref().map(|h| {
+                    Arc::new(Callback {
+                        handler: h.clone_ref(py),
+                        locals: locals.as_ref().map(|l| l.clone_ref(py)),
+                    })
+                })
+            })
+        };
+
+        let disconnect_handler = callback(&self.disconnect_handler);
+        let reconnect_policy = self.reconnect_policy.clone();
+        let mon = monitor.clone();
+        builder.connector(
+            move |host: String,
+                  port: u16,
+                  tls_config: Arc<ClientConfig>,
+                  err: Option<AcceptError>| {
+                let disconnect_handler = disconnect_handler.clone();
+                let policy = reconnect_policy.clone();
+                let monitor = mon.clone();
+                async move {
+                    let Some(mut err) = err else {
+                        // the initial connection is retried by the caller, not by the policy
+                        return default_connect(host, port, tls_config, None).await;
+                    };
+                    loop {
+                        let attempt = monitor.reconnecting(&err);
+                        if let Some(policy) = &policy {
+                            if let Some(reason) = policy.exhausted(attempt, monitor.outage()) {
+                                info!("Canceling connection to {host}:{port}, {reason}");
+                                monitor.give_up(reason);
+                                return Err(ConnectError::Canceled);
+                            }
+                            tokio::time::sleep(policy.delay(attempt)).await;
+                        }
                         // call disconnect python handler
-                        if let Some(handler) =
-                            Python::with_gil(|py| disconn_fn.as_ref().map(|h| h.clone_ref(py)))
-                        {
-                            if let Some(err) = err.clone() {
-                                Python::with_gil(|py| -> PyResult<()> {
-                                    handler
-                                        .call(py, (format!("{host}:{port}"), err.to_string()), None)
-                                        .map(|_o| ())
-                                })
+                        if let Some(handler) = &disconnect_handler {
+                            handler
+                                .call((format!("{host}:{port}"), err.to_string()))
+                                .await
                                 .map_err(|e| {
                                     info!("Canceling connection to {host}:{port} due to {e}");
+                                    monitor.closed(Some(e));
                                     ConnectError::Canceled
                                 })?;
-                            }
-                        };
+                        }
                         // call the upstream connector
-                        default_connect(host, port, tls_config, err).await
+                        match default_connect(host.clone(), port, tls_config.clone(), Some(err))
+                            .await
+                        {
+                            Ok(conn) => {
+                                monitor.connected();
+                                return Ok(conn);
+                            }
+                            // with a policy, keep retrying here rather than on the upstream backoff
+                            Err(e) if policy.is_some() => {
+                                err = AcceptError::Reconnect(Arc::new(e));
+                            }
+                            Err(e) => return Err(e),
+                        }
                     }
-                },
-            );
+                }
+            },
+        );
+
+        let stop_handler = callback(&self.stop_handler);
+        let mon = monitor.clone();
+        builder.handle_stop_command(move |_req| {
+            let handler = stop_handler.clone();
+            let monitor = mon.clone();
+            async move {
+                monitor.stop_command();
+                call_command_handler(handler, ()).await
+            }
         });
+
+        let restart_handler = callback(&self.restart_handler);
+        let mon = monitor.clone();
+        builder.handle_restart_command(move |_req| {
+            let handler = restart_handler.clone();
+            let monitor = mon.clone();
+            async move {
+                monitor.restart_command();
+                call_command_handler(handler, ()).await
+            }
+        });
+
+        let update_handler = callback(&self.update_handler);
+        let mon = monitor.clone();
+        builder.handle_update_command(move |req: Update| {
+            let handler = update_handler.clone();
+            let monitor = mon.clone();
+            async move {
+                monitor.update_command(req.version.clone(), req.permit_major_version);
+                call_command_handler(handler, (req.version, req.permit_major_version)).await
+            }
+        });
+
+        let heartbeat_handler = callback(&self.heartbeat_handler);
+        let mon = monitor.clone();
+        builder.handle_heartbeat(move |latency: Option<Duration>| {
+            let handler = heartbeat_handler.clone();
+            let monitor = mon.clone();
+            let millis = latency.and_then(|d| u32::try_from(d.as_millis()).ok());
+            async move {
+                monitor.heartbeat(latency);
+                match (handler, millis) {
+                    (Some(h), Some(m)) => h.call((m,)).await,
+                    (Some(h), None) => h.call(()).await,
+                    (None, _) => Ok(()),
+                }
+                .map_err(|e| e.into())
+            }
+        });
+
+        builder
     }
 
     pub async fn async_connect<'a>(&self) -> Result<Session, PyErr> {
+        self.connecting().await
+    }
+
+    /// Begin connecting a session with the current configuration, in a future which doesn't
+    /// borrow the builder.
+    pub(crate) fn connecting(
+        &self,
+    ) -> impl Future<Output = Result<Session, PyErr>> + Send + 'static {
         self.handle_default_auth_token();
-        let builder = self.raw_builder.lock().clone();
-        do_connect(builder, self.auth_token_set).await
+        let monitor = SessionMonitor::new();
+        let builder = self.build(&monitor);
+        do_connect(builder, monitor, self.auth_token_set)
+    }
+}
+
+/// A python handler, with the asyncio context of the session's connect call to run
+/// coroutines returned by ``async def`` handlers in.
+struct Callback {
+    handler: PyObject,
+    locals: Option<TaskLocals>,
+}
+
+impl Callback {
+    /// Call the handler, awaiting its result on the owning event loop if it is a
+    /// coroutine, and describe any exception it raises.
+    async fn call(&self, args: impl IntoPy<Py<PyTuple>>) -> Result<(), String> {
+        let awaiting = Python::with_gil(|py| -> PyResult<_> {
+            let res = self.handler.bind(py).call1(args)?;
+            if !res.hasattr(intern!(py, "__await__"))? {
+                return Ok(None);
+            }
+            let locals = self.locals.as_ref().ok_or_else(|| {
+                py_err::<SessionError>(
+                    "async handlers require the session to be connected from a running event loop",
+                )
+            })?;
+            if locals
+                .event_loop(py)
+                .call_method0(intern!(py, "is_closed"))?
+                .is_truthy()?
+            {
+                return Err(py_err::<SessionError>(
+                    "the event loop which connected the session is closed",
+                ));
+            }
+            pyo3_async_runtimes::into_future_with_locals(locals, res).map(Some)
+        });
+        if let Some(fut) = awaiting.map_err(|e| format!("Callback error {e:?}"))? {
+            fut.await.map_err(|e| format!("Callback error {e:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Call a python command handler, responding as upstream does when there is none.
+async fn call_command_handler(
+    handler: Option<Arc<Callback>>,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> Result<(), String> {
+    match handler {
+        Some(h) => h.call(args).await,
+        None => Err(NOT_IMPLEMENTED.into()),
     }
 }
 
@@ -166,10 +357,27 @@ impl SessionBuilder {
                     .clone(),
             )),
             disconnect_handler: None,
+            stop_handler: None,
+            restart_handler: None,
+            update_handler: None,
+            heartbeat_handler: None,
+            reconnect_policy: None,
             auth_token_set: false,
         }
     }
 
+    /// Create a session builder from the ``authtoken``, ``server_addr`` and ``root_cas``
+    /// of an ngrok agent configuration file, version 2 or 3. If the file has no authtoken,
+    /// the NGROK_AUTHTOKEN environment variable is used.
+    ///
+    /// See :meth:`ngrok.forward_from_config` to also start the file's tunnels.
+    ///
+    /// :param str path: The path of the ngrok agent configuration file, e.g. "ngrok.yml".
+    #[staticmethod]
+    pub fn from_config(py: Python, path: String) -> PyResult<Self> {
+        AgentConfig::load(py, &path)?.session_builder()
+    }
+
     /// Configures the session to authenticate with the provided authtoken. You
     /// can `find your existing authtoken`_ or `create a new one`_ in the ngrok
     /// dashboard.
@@ -229,12 +437,18 @@ impl SessionBuilder {
     /// details.
     ///
     /// .. _heartbeat_interval parameter in the ngrok docs: https://ngrok.com/docs/ngrok-agent/config#heartbeat_interval
-    pub fn heartbeat_interval(self_: PyRefMut<Self>, heartbeat_interval: u32) -> PyRefMut<Self> {
+    pub fn heartbeat_interval(
+        self_: PyRefMut<Self>,
+        heartbeat_interval: u32,
+    ) -> PyResult<PyRefMut<Self>> {
+        let mut res = Ok(());
         self_.set(|b| {
-            b.heartbeat_interval(Duration::new(heartbeat_interval.into(), 0))
-                .expect("fixme");
+            res = b
+                .heartbeat_interval(Duration::new(heartbeat_interval.into(), 0))
+                .map(|_| ())
+                .map_err(|e| py_err::<ConfigError>(format!("{e}")));
         });
-        self_
+        res.map(|_| self_)
     }
 
     /// Configures the duration to wait for a response to a heartbeat before
@@ -244,12 +458,18 @@ impl SessionBuilder {
     /// details.
     ///
     /// .. _heartbeat_tolerance parameter in the ngrok docs: https://ngrok.com/docs/ngrok-agent/config#heartbeat_tolerance
-    pub fn heartbeat_tolerance(self_: PyRefMut<Self>, heartbeat_tolerance: u32) -> PyRefMut<Self> {
+    pub fn heartbeat_tolerance(
+        self_: PyRefMut<Self>,
+        heartbeat_tolerance: u32,
+    ) -> PyResult<PyRefMut<Self>> {
+        let mut res = Ok(());
         self_.set(|b| {
-            b.heartbeat_tolerance(Duration::new(heartbeat_tolerance.into(), 0))
-                .expect("fixme");
+            res = b
+                .heartbeat_tolerance(Duration::new(heartbeat_tolerance.into(), 0))
+                .map(|_| ())
+                .map_err(|e| py_err::<ConfigError>(format!("{e}")));
         });
-        self_
+        res.map(|_| self_)
     }
 
     /// Configures the opaque, machine-readable metadata string for this session.
@@ -273,12 +493,15 @@ impl SessionBuilder {
     /// See the `server_addr parameter in the ngrok docs`_ for additional details.
     ///
     /// .. _server_addr parameter in the ngrok docs: https://ngrok.com/docs/ngrok-agent/config#server_addr
-    pub fn server_addr(self_: PyRefMut<Self>, addr: String) -> PyRefMut<Self> {
+    pub fn server_addr(self_: PyRefMut<Self>, addr: String) -> PyResult<PyRefMut<Self>> {
+        let mut res = Ok(());
         self_.set(|b| {
-            b.server_addr(&addr)
-                .unwrap_or_else(|_| panic!("failed to parse addr: {addr}"));
+            res = b
+                .server_addr(&addr)
+                .map(|_| ())
+                .map_err(|e| py_err::<ConfigError>(format!("failed to parse addr: {addr}, {e}")));
         });
-        self_
+        res.map(|_| self_)
     }
 
     /// Sets the file path to a default certificate in PEM format to validate ngrok Session TLS connections.
@@ -289,12 +512,14 @@ impl SessionBuilder {
     /// Corresponds to the `root_cas parameter in the ngrok docs`_
     ///
     /// .. _root_cas parameter in the ngrok docs: https://ngrok.com/docs/ngrok-agent/config#root_cas
-    pub fn root_cas(self_: PyRefMut<Self>, root_cas: String) -> PyRefMut<Self> {
+    pub fn root_cas(self_: PyRefMut<Self>, root_cas: String) -> PyResult<PyRefMut<Self>> {
+        let mut res = Ok(());
         self_.set(|b| {
-            b.root_cas(&root_cas)
-                .unwrap_or_else(|_| panic!("failed to invoke root_cas: {root_cas}"));
+            res = b.root_cas(&root_cas).map(|_| ()).map_err(|e| {
+                py_err::<ConfigError>(format!("failed to invoke root_cas: {root_cas}, {e}"))
+            });
         });
-        self_
+        res.map(|_| self_)
     }
 
     /// Configures the TLS certificate used to connect to the ngrok service while
@@ -319,36 +544,63 @@ impl SessionBuilder {
     /// connect the session to, e.g. "example.com:443", and the message from the error
     /// that occurred. Raising an exception in the handler will cause the Session to
     /// throw an uncaught error instead of reconnecting.
+    ///
+    /// The handler may be an ``async def`` function. It then runs on the event loop
+    /// which connected the session, and reconnecting waits for it to finish.
     pub fn handle_disconnection(mut self_: PyRefMut<Self>, handler: PyObject) -> PyRefMut<Self> {
         self_.disconnect_handler = Some(handler);
-        self_.update_connector();
         self_
     }
 
+    /// Configures how the session reconnects to the ngrok service after a
+    /// disconnection. Each attempt waits ``min_backoff`` seconds, multiplied by
+    /// ``multiplier`` for every failed attempt up to ``max_backoff``, and randomly
+    /// varied by up to the ``jitter`` fraction of the delay.
+    ///
+    /// Once ``max_attempts`` attempts have failed, or the session has been disconnected
+    /// for ``give_up_after`` seconds, the session stops reconnecting and closes. Its
+    /// listeners then raise :class:`ReconnectError`.
+    ///
+    /// Without a policy, the session reconnects indefinitely.
+    #[pyo3(signature = (min_backoff=0.5, max_backoff=30.0, multiplier=2.0, jitter=0.1, max_attempts=None, give_up_after=None))]
+    pub fn reconnect_policy(
+        mut self_: PyRefMut<Self>,
+        min_backoff: f64,
+        max_backoff: f64,
+        multiplier: f64,
+        jitter: f64,
+        max_attempts: Option<u32>,
+        give_up_after: Option<f64>,
+    ) -> PyResult<PyRefMut<Self>> {
+        self_.reconnect_policy = Some(ReconnectPolicy::new(
+            min_backoff,
+            max_backoff,
+            multiplier,
+            jitter,
+            max_attempts,
+            give_up_after,
+        )?);
+        Ok(self_)
+    }
+
     /// Configures a function which is called when the ngrok service requests that
     /// this `Session`_ stops. Your application may choose to interpret this callback
     /// as a request to terminate the `Session`_ or the entire process.
     ///
     /// Errors returned by this function will be visible to the ngrok dashboard or
-    /// API as the response to the Stop operation.
+    /// API as the response to the Stop operation. An ``async def`` handler is awaited
+    /// on the event loop which connected the session before responding.
     ///
     /// Do not block inside this callback. It will cause the Dashboard or API
     /// stop operation to time out. Do not call `std::process::exit <https://doc.rust-lang.org/std/process/fn.exit.html>`_ inside this
     /// callback, it will also cause the operation to time out.
     ///
     /// .. _Session: https://docs.rs/ngrok/0.11.0/ngrok/struct.Session.html
-    pub fn handle_stop_command(self_: PyRefMut<'_, Self>, handler: PyObject) -> PyRefMut<'_, Self> {
-        self_.set(|b| {
-            b.handle_stop_command(move |_req| {
-                let handler = Python::with_gil(|py| handler.clone_ref(py));
-                async move {
-                    Python::with_gil(|py| -> PyResult<()> {
-                        handler.call(py, (), None).map(|_o| ())
-                    })
-                    .map_err(|e| format!("Callback error {e:?}"))
-                }
-            });
-        });
+    pub fn handle_stop_command(
+        mut self_: PyRefMut<'_, Self>,
+        handler: PyObject,
+    ) -> PyRefMut<'_, Self> {
+        self_.stop_handler = Some(handler);
         self_
     }
 
@@ -358,7 +610,8 @@ impl SessionBuilder {
     /// process.
     ///
     /// Errors returned by this function will be visible to the ngrok dashboard or
-    /// API as the response to the Restart operation.
+    /// API as the response to the Restart operation. An ``async def`` handler is
+    /// awaited on the event loop which connected the session before responding.
     ///
     /// Do not block inside this callback. It will cause the Dashboard or API
     /// stop operation to time out. Do not call `std::process::exit <https://doc.rust-lang.org/std/process/fn.exit.html>`_ inside this
@@ -366,20 +619,10 @@ impl SessionBuilder {
     ///
     /// .. _Session: https://docs.rs/ngrok/0.11.0/ngrok/struct.Session.html
     pub fn handle_restart_command(
-        self_: PyRefMut<'_, Self>,
+        mut self_: PyRefMut<'_, Self>,
         handler: PyObject,
     ) -> PyRefMut<'_, Self> {
-        self_.set(|b| {
-            b.handle_restart_command(move |_req| {
-                let handler = Python::with_gil(|py| handler.clone_ref(py));
-                async move {
-                    Python::with_gil(|py| -> PyResult<()> {
-                        handler.call(py, (), None).map(|_o| ())
-                    })
-                    .map_err(|e| format!("Callback error {e:?}"))
-                }
-            });
-        });
+        self_.restart_handler = Some(handler);
         self_
     }
 
@@ -389,7 +632,8 @@ impl SessionBuilder {
     /// invoke some other application-specific behavior.
     ///
     /// Errors returned by this function will be visible to the ngrok dashboard or
-    /// API as the response to the Restart operation.
+    /// API as the response to the Restart operation. Like the other command handlers,
+    /// it may be an ``async def`` function.
     ///
     /// Do not block inside this callback. It will cause the Dashboard or API
     /// stop operation to time out. Do not call `std::process::exit <https://doc.rust-lang.org/std/process/fn.exit.html>`_ inside this
@@ -397,22 +641,10 @@ impl SessionBuilder {
     ///
     /// .. _Session: https://docs.rs/ngrok/0.11.0/ngrok/struct.Session.html
     pub fn handle_update_command(
-        self_: PyRefMut<'_, Self>,
+        mut self_: PyRefMut<'_, Self>,
         handler: PyObject,
     ) -> PyRefMut<'_, Self> {
-        self_.set(|b| {
-            b.handle_update_command(move |req: Update| {
-                let handler = Python::with_gil(|py| handler.clone_ref(py));
-                async move {
-                    Python::with_gil(|py| -> PyResult<()> {
-                        handler
-                            .call(py, (req.version, req.permit_major_version), None)
-                            .map(|_o| ())
-                    })
-                    .map_err(|e| format!("Callback error {e:?}"))
-                }
-            });
-        });
+        self_.update_handler = Some(handler);
         self_
     }
 
@@ -420,51 +652,96 @@ impl SessionBuilder {
     /// with the latency in milliseconds.
     ///
     /// If the handler returns an error, the heartbeat task will exit, resulting
-    /// in the session eventually dying as well.
-    pub fn handle_heartbeat(self_: PyRefMut<'_, Self>, handler: PyObject) -> PyRefMut<'_, Self> {
-        self_.set(|b| {
-            b.handle_heartbeat(move |latency: Option<Duration>| {
-                let handler = Python::with_gil(|py| handler.clone_ref(py));
-                let millis = latency.and_then(|d| u32::try_from(d.as_millis()).ok());
-                async move {
-                    Python::with_gil(|py| -> PyResult<()> {
-                        if let Some(m) = millis {
-                            handler.call(py, (m,), None)
-                        } else {
-                            handler.call(py, (), None)
-                        }
-                        .map(|_o| ())
-                    })
-                    .map_err(|e| format!("Callback error {e:?}").into())
-                }
-            });
-        });
+    /// in the session eventually dying as well. An ``async def`` handler runs on
+    /// the event loop which connected the session.
+    pub fn handle_heartbeat(
+        mut self_: PyRefMut<'_, Self>,
+        handler: PyObject,
+    ) -> PyRefMut<'_, Self> {
+        self_.heartbeat_handler = Some(handler);
         self_
     }
 
     /// Attempt to establish an ngrok session using the current configuration.
     pub fn connect<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
-        self.handle_default_auth_token();
-        let builder = self.raw_builder.lock().clone();
-        let auth_token_set = self.auth_token_set;
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            do_connect(builder, auth_token_set).await
-        })
+        backend::future_into_py(py, self.connecting())
+    }
+
+    /// Establish an ngrok session using the current configuration, blocking until it is
+    /// connected. The GIL is released while waiting.
+    ///
+    /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+    /// :return: The connected session.
+    /// :rtype: SyncSession
+    #[pyo3(signature = (timeout=None))]
+    pub fn connect_sync(&self, py: Python, timeout: Option<f64>) -> PyResult<SyncSession> {
+        wrapper::block_on_timeout_cleanup(py, timeout, "connect", self.connecting(), close_late)
+            .map(SyncSession::from)
     }
 }
 
-async fn do_connect(builder: NgrokSessionBuilder, auth_token_set: bool) -> Result<Session, PyErr> {
+/// Close a session which connected after a blocking connect gave up on it.
+async fn close_late(session: Session) {
+    warn!(
+        "Closing session {:?} which connected after its connect timed out",
+        session.id()
+    );
+    if let Err(e) = session.close_if_open().await {
+        warn!("Error closing session {:?}: {e}", session.id());
+    }
+}
+
+async fn do_connect(
+    builder: NgrokSessionBuilder,
+    monitor: Arc<SessionMonitor>,
+    auth_token_set: bool,
+) -> Result<Session, PyErr> {
     builder
         .connect()
         .await
         .map(|s| {
             let maybe_with = if auth_token_set { "with" } else { "without" };
             info!("Session created {maybe_with} auth token");
-            Session {
+            monitor.connected();
+            register_monitor(s.id(), &monitor);
+            let session = Session {
                 raw_session: Arc::new(SyncMutex::new(s)),
+                monitor: monitor.clone(),
+            };
+            register_session(&session);
+            session
+        })
+        .inspect_err(|e| monitor.closed(Some(e.to_string())))
+        .map_err(|e| match e {
+            ConnectError::Auth(_) => {
+                py_ngrok_err::<AuthError>("failed to authenticate session", &e)
             }
+            _ => py_ngrok_err::<SessionError>("failed to connect session", &e),
+        })
+}
+
+/// Remember a connected session for `live_sessions`.
+fn register_session(session: &Session) {
+    let mut sessions = LIVE_SESSIONS.lock();
+    sessions.retain(|(raw, _)| raw.strong_count() > 0);
+    sessions.push((
+        Arc::downgrade(&session.raw_session),
+        Arc::downgrade(&session.monitor),
+    ));
+}
+
+/// The connected sessions which are still referenced, whether or not they have been closed.
+pub(crate) fn live_sessions() -> Vec<Session> {
+    LIVE_SESSIONS
+        .lock()
+        .iter()
+        .filter_map(|(raw, monitor)| {
+            Some(Session {
+                raw_session: raw.upgrade()?,
+                monitor: monitor.upgrade()?,
+            })
         })
-        .map_err(|e| py_ngrok_err("failed to connect session", &e))
+        .collect()
 }
 
 impl Drop for SessionBuilder {
@@ -478,6 +755,7 @@ impl Drop for SessionBuilder {
 #[derive(Clone)]
 pub(crate) struct Session {
     raw_session: Arc<SyncMutex<NgrokSession>>,
+    monitor: Arc<SessionMonitor>,
 }
 
 #[pymethods]
@@ -486,6 +764,20 @@ impl Session {
         "ngrok_session".to_string()
     }
 
+    /// The state of the session: "connecting", "connected", "reconnecting" or "closed".
+    #[getter]
+    pub fn state(&self) -> &'static str {
+        self.monitor.state().as_str()
+    }
+
+    /// Iterate the session's lifecycle events with ``async for``. Only events which
+    /// occur after this call are yielded, and iteration ends once the session closes.
+    ///
+    /// :return: An async iterator of :class:`SessionEvent`.
+    pub fn events(&self) -> SessionEvents {
+        self.monitor.subscribe()
+    }
+
     /// Start building a Listener backing an HTTP endpoint.
     pub fn http_endpoint(&self) -> HttpListenerBuilder {
         let session = self.raw_session.lock().clone();
@@ -513,37 +805,100 @@ impl Session {
     /// Retrieve a list of this session's non-closed Listeners, in no particular order.
     pub fn get_listeners<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
         let session_id = self.raw_session.lock().id();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            list_listeners(Some(session_id)).await
-        })
+        backend::future_into_py(py, async move { list_listeners(Some(session_id)).await })
     }
 
     /// Close a listener with the given ID.
     pub fn close_listener<'a>(&self, py: Python<'a>, id: String) -> PyResult<Bound<'a, PyAny>> {
-        let session = self.raw_session.lock().clone();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            let res = session
-                .close_tunnel(id.clone())
-                .await
-                .map_err(|e| py_ngrok_err("failed to connect session", &e));
-
-            if res.is_ok() {
-                // remove our reference to allow it to drop
-                remove_global_listener(&id).await?;
-            }
-            res
-        })
+        let session = self.clone();
+        backend::future_into_py(py, async move { session.async_close_listener(id).await })
     }
 
     /// Close the ngrok session.
     pub fn close<'a>(&self, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let session = self.clone();
+        backend::future_into_py(py, async move { session.async_close().await })
+    }
+
+    fn __aenter__<'a>(slf: PyRef<'a, Self>, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let session: Py<Self> = slf.into();
+        backend::future_into_py(py, async move { Ok(session) })
+    }
+
+    /// Close the session, and with it all of its listeners, on leaving an ``async with`` block.
+    fn __aexit__<'a>(
+        &self,
+        py: Python<'a>,
+        _exc_type: &Bound<'a, PyAny>,
+        _exc_value: &Bound<'a, PyAny>,
+        _traceback: &Bound<'a, PyAny>,
+    ) -> PyResult<Bound<'a, PyAny>> {
+        let session = self.clone();
+        backend::future_into_py(
+            py,
+            async move { session.close_if_open().await.map(|_| false) },
+        )
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Close the session, and with it all of its listeners, on leaving a ``with`` block.
+    fn __exit__(
+        &self,
+        py: Python,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<bool> {
+        let session = self.clone();
+        wrapper::block_on(py, async move { session.close_if_open().await }).map(|_| false)
+    }
+}
+
+impl Session {
+    pub(crate) fn id(&self) -> String {
+        self.raw_session.lock().id()
+    }
+
+    pub(crate) fn raw_session(&self) -> NgrokSession {
+        self.raw_session.lock().clone()
+    }
+
+    pub(crate) async fn async_close_listener(&self, id: String) -> PyResult<()> {
+        let session = self.raw_session();
+        let res = session
+            .close_tunnel(id.clone())
+            .await
+            .map_err(|e| py_ngrok_err::<ListenerError>("failed to close listener", &e));
+
+        if res.is_ok() {
+            // remove our reference to allow it to drop
+            remove_global_listener(&id).await?;
+        }
+        res
+    }
+
+    pub(crate) async fn async_close(&self) -> PyResult<()> {
         let mut session = self.raw_session.lock().clone();
-        pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            session
-                .close()
-                .await
-                .map_err(|e| py_ngrok_err("failed to close listener", &e))
-        })
+        let res = session
+            .close()
+            .await
+            .map_err(|e| py_ngrok_err::<SessionError>("failed to close session", &e));
+        self.monitor.closed(None);
+        // the listeners went down with the session, drop our references to them
+        remove_session_listeners(&self.id()).await;
+        res
+    }
+
+    /// Close the session unless it is already closed, still dropping its listeners.
+    pub(crate) async fn close_if_open(&self) -> PyResult<()> {
+        if self.monitor.state() == SessionState::Closed {
+            remove_session_listeners(&self.id()).await;
+            return Ok(());
+        }
+        self.async_close().await
     }
 }
 
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashSet,
+    panic::{
+        self,
+        AssertUnwindSafe,
+    },
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    time::Duration,
+};
+
+use futures::future;
+use pyo3::{
+    exceptions::{
+        PyNotImplementedError,
+        PySystemExit,
+    },
+    pyfunction,
+    types::{
+        PyAnyMethods,
+        PyCFunction,
+        PyModule,
+        PyTuple,
+        PyTupleMethods,
+    },
+    wrap_pyfunction_bound,
+    Bound,
+    PyResult,
+    Python,
+};
+use tracing::{
+    debug,
+    info,
+};
+
+use crate::{
+    connect::SESSION,
+    listener::{
+        drain_duration,
+        list_listeners,
+        release_sockets,
+    },
+    session::live_sessions,
+    wrapper,
+};
+
+// set once shutdown starts, so a repeated signal exits without waiting on it again
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+// how long interpreter exit may wait on closing listeners and sessions
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Close all listeners and sessions when the process receives one of the given signals,
+/// then exit with status 128 plus the signal number. A second signal exits at once.
+///
+/// Inside a running asyncio loop the handlers are added to the loop, and the shutdown runs
+/// as a task on it. Otherwise they are installed with :func:`signal.signal`, which must
+/// be called from the main thread.
+///
+/// :param signals: The signals to handle, defaulting to SIGINT and SIGTERM.
+/// :param float or None drain_timeout: The seconds to wait for each listener's forwarded connections to finish, see :meth:`Listener.close`.
+#[pyfunction]
+#[pyo3(signature = (signals=None, drain_timeout=None))]
+pub fn install_signal_handlers(
+    py: Python,
+    signals: Option<Vec<i32>>,
+    drain_timeout: Option<f64>,
+) -> PyResult<()> {
+    let drain_timeout = drain_timeout.map(drain_duration).transpose()?;
+    let signal = PyModule::import_bound(py, "signal")?;
+    let signals = match signals {
+        Some(signals) => signals,
+        None => vec![
+            signal.getattr("SIGINT")?.extract()?,
+            signal.getattr("SIGTERM")?.extract()?,
+        ],
+    };
+
+    let handler = PyCFunction::new_closure_bound(py, None, None, move |args, _kwargs| {
+        on_signal(args, drain_timeout)
+    })?;
+    let running_loop = PyModule::import_bound(py, "asyncio")?
+        .call_method0("get_running_loop")
+        .ok();
+    for signum in signals {
+        if let Some(running_loop) = &running_loop {
+            // not available on windows, which falls back to signal.signal
+            match running_loop.call_method1("add_signal_handler", (signum, &handler, signum)) {
+                Ok(_) => continue,
+                Err(e) if e.is_instance_of::<PyNotImplementedError>(py) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        signal.call_method1("signal", (signum, &handler))?;
+    }
+    Ok(())
+}
+
+/// Handle a signal, called with the signal number first.
+fn on_signal(args: &Bound<'_, PyTuple>, drain_timeout: Option<Duration>) -> PyResult<()> {
+    let py = args.py();
+    let signum: i32 = args.get_item(0)?.extract()?;
+    let code = 128 + signum;
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return Err(PySystemExit::new_err(code));
+    }
+    info!("Received signal {signum}, closing all listeners and sessions");
+
+    if PyModule::import_bound(py, "asyncio")?
+        .call_method0("get_running_loop")
+        .is_err()
+    {
+        wrapper::block_on(py, close_all(drain_timeout));
+        return Err(PySystemExit::new_err(code));
+    }
+
+    // exit from a callback of the loop once done, which asyncio propagates out of the loop
+    let done = pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        close_all(drain_timeout).await;
+        Ok(())
+    })?;
+    let exit = PyCFunction::new_closure_bound(py, None, None, move |_args, _kwargs| {
+        Err::<(), _>(PySystemExit::new_err(code))
+    })?;
+    done.call_method1("add_done_callback", (exit,))?;
+    Ok(())
+}
+
+/// Register `finalize` to run at interpreter exit.
+pub(crate) fn register_finalizer(py: Python) -> PyResult<()> {
+    PyModule::import_bound(py, "atexit")?
+        .call_method1("register", (wrap_pyfunction_bound!(finalize, py)?,))?;
+    Ok(())
+}
+
+/// Close all listeners and sessions, and release their sockets, at interpreter exit. Gives
+/// up after FINALIZE_TIMEOUT rather than hang the exit, and never raises.
+#[pyfunction]
+fn finalize(py: Python) {
+    // modules may already be torn down if the interpreter got this far without us
+    let finalizing = PyModule::import_bound(py, "sys")
+        .and_then(|sys| sys.call_method0("is_finalizing")?.extract::<bool>())
+        .unwrap_or(true);
+    if finalizing {
+        return;
+    }
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let closed = wrapper::block_on(py, tokio::time::timeout(FINALIZE_TIMEOUT, close_all(None)));
+        if closed.is_err() {
+            debug!("Timed out closing listeners and sessions at exit");
+        }
+        release_sockets(py);
+    }));
+    if res.is_err() {
+        debug!("Error closing listeners and sessions at exit");
+    }
+}
+
+/// Close every listener, waiting up to drain_timeout for their forwarded connections, and
+/// then every session, including the one shared by :meth:`forward`.
+pub(crate) async fn close_all(drain_timeout: Option<Duration>) {
+    let listeners = list_listeners(None).await.unwrap_or_default();
+    // sessions which are only still referenced by their listeners
+    let mut orphans: Vec<_> = listeners.iter().map(|l| l.raw_session()).collect();
+    let results =
+        future::join_all(listeners.iter().map(|l| l.async_close_drain(drain_timeout))).await;
+    for (listener, res) in listeners.iter().zip(results) {
+        if let Err(e) = res {
+            debug!("Error closing listener {}: {e:?}", listener.id());
+        }
+    }
+
+    let mut sessions = live_sessions();
+    sessions.extend(SESSION.lock().await.take());
+    let mut seen = HashSet::new();
+    sessions.retain(|s| seen.insert(s.id()));
+    orphans.retain(|s| seen.insert(s.id()));
+
+    let results = future::join_all(sessions.iter().map(|s| s.close_if_open())).await;
+    for (session, res) in sessions.iter().zip(results) {
+        if let Err(e) = res {
+            debug!("Error closing session {}: {e:?}", session.id());
+        }
+    }
+    for mut session in orphans {
+        if let Err(e) = session.close().await {
+            debug!("Error closing session {}: {e:?}", session.id());
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use pyo3::{
+    intern,
+    pyclass,
+    pyfunction,
+    pymethods,
+    types::{
+        PyAnyMethods,
+        PyBytes,
+        PyCFunction,
+        PyDict,
+        PyModule,
+        PyTuple,
+        PyTupleMethods,
+    },
+    Bound,
+    IntoPy,
+    Py,
+    PyAny,
+    PyObject,
+    PyResult,
+    Python,
+};
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    sync::{
+        mpsc,
+        watch,
+    },
+};
+use tracing::debug;
+
+use crate::{
+    connection::{
+        bytes_from,
+        Connection,
+    },
+    listener::{
+        accept_connection,
+        Listener,
+    },
+};
+
+const READ_CHUNK: usize = 64 * 1024;
+const HIGH_WATER: usize = 64 * 1024;
+const LOW_WATER: usize = HIGH_WATER / 4;
+
+/// Start serving a listener with asyncio streams, mirroring `asyncio.start_server`.
+/// Each accepted connection is passed to ``client_connected_cb`` as an
+/// ``(asyncio.StreamReader, asyncio.StreamWriter)`` pair. If the callback is a
+/// coroutine function, it is scheduled as a task on the running loop.
+///
+/// Serving stops when the listener is closed.
+///
+/// :param client_connected_cb: The callback to hand each connection's streams to.
+/// :param Listener listener: The listener to accept connections from.
+/// :return: A task to await for the :class:`Listener`, once serving has started.
+/// :rtype: Task
+#[pyfunction]
+#[pyo3(text_signature = "(client_connected_cb, listener)")]
+pub(crate) fn start_server(
+    py: Python<'_>,
+    client_connected_cb: PyObject,
+    listener: Listener,
+) -> PyResult<Bound<'_, PyAny>> {
+    let asyncio = PyModule::import_bound(py, intern!(py, "asyncio"))?;
+    let event_loop = asyncio
+        .call_method0(intern!(py, "get_running_loop"))?
+        .unbind();
+    let id = listener.id();
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        tokio::spawn(serve(id, event_loop, client_connected_cb));
+        Ok(listener)
+    })
+}
+
+/// Accept loop handing each connection to a new stream transport.
+async fn serve(id: String, event_loop: PyObject, client_connected_cb: PyObject) {
+    loop {
+        match accept_connection(&id).await {
+            Ok(Some(conn)) => {
+                let res = Python::with_gil(|py| {
+                    StreamTransport::start(py, conn, &event_loop, &client_connected_cb)
+                });
+                if let Err(e) = res {
+                    debug!("Error starting stream transport on {id}: {e:?}");
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                debug!("Stopping stream server on {id}: {e:?}");
+                break;
+            }
+        }
+    }
+}
+
+enum WriteMsg {
+    Data(Vec<u8>),
+    Eof,
+    Close,
+    // the read side failed, so the connection is lost with this error
+    Failed(std::io::Error),
+}
+
+/// State shared between the transport and its background read and write tasks.
+struct TransportState {
+    closing: AtomicBool,
+    closed: watch::Sender<bool>,
+    buffered: AtomicUsize,
+    // only touched from the event loop thread
+    writing_paused: AtomicBool,
+    reading_paused: watch::Sender<bool>,
+}
+
+/// An ``asyncio.Transport`` backed by an ngrok :class:`Connection`.
+#[pyclass]
+pub(crate) struct StreamTransport {
+    conn: Connection,
+    protocol: PyObject,
+    tx: mpsc::UnboundedSender<WriteMsg>,
+    state: Arc<TransportState>,
+}
+
+impl StreamTransport {
+    /// Build the reader, writer and protocol for a connection and start its IO tasks.
+    fn start(
+        py: Python,
+        conn: Connection,
+        event_loop: &PyObject,
+        client_connected_cb: &PyObject,
+    ) -> PyResult<()> {
+        let asyncio = PyModule::import_bound(py, intern!(py, "asyncio"))?;
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item(intern!(py, "loop"), event_loop)?;
+        let reader = asyncio
+            .getattr(intern!(py, "StreamReader"))?
+            .call((), Some(&kwargs))?;
+        let protocol = asyncio
+            .getattr(intern!(py, "StreamReaderProtocol"))?
+            .call((reader, client_connected_cb), Some(&kwargs))?
+            .unbind();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let state = Arc::new(TransportState {
+            closing: AtomicBool::new(false),
+            closed: watch::channel(false).0,
+            buffered: AtomicUsize::new(0),
+            writing_paused: AtomicBool::new(false),
+            reading_paused: watch::channel(false).0,
+        });
+        let transport = Py::new(
+            py,
+            StreamTransport {
+                conn: conn.clone(),
+                protocol: protocol.clone_ref(py),
+                tx: tx.clone(),
+                state: state.clone(),
+            },
+        )?;
+
+        call_soon(
+            py,
+            event_loop,
+            protocol.getattr(py, intern!(py, "connection_made"))?,
+            (transport.clone_ref(py),),
+        )?;
+        tokio::spawn(read_loop(
+            conn.clone(),
+            tx.clone(),
+            state.clone(),
+            event_loop.clone_ref(py),
+            protocol.clone_ref(py),
+            transport.clone_ref(py),
+        ));
+        tokio::spawn(write_loop(
+            conn,
+            rx,
+            state,
+            event_loop.clone_ref(py),
+            protocol,
+            transport,
+        ));
+        Ok(())
+    }
+
+    fn send(&self, msg: WriteMsg) {
+        if self.tx.send(msg).is_err() {
+            debug!("Write to closed transport");
+        }
+    }
+}
+
+#[pymethods]
+#[allow(dead_code)]
+impl StreamTransport {
+    pub fn write(&self, py: Python, data: &Bound<'_, PyAny>) -> PyResult<()> {
+        let data = bytes_from(data)?;
+        if data.is_empty() || self.state.closing.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let buffered = self.state.buffered.fetch_add(data.len(), Ordering::SeqCst) + data.len();
+        self.send(WriteMsg::Data(data));
+        if buffered > HIGH_WATER && !self.state.writing_paused.swap(true, Ordering::SeqCst) {
+            self.protocol
+                .call_method0(py, intern!(py, "pause_writing"))?;
+        }
+        Ok(())
+    }
+
+    pub fn writelines(&self, py: Python, lines: &Bound<'_, PyAny>) -> PyResult<()> {
+        let mut data = vec![];
+        for line in lines.iter()? {
+            data.extend(bytes_from(&line?)?);
+        }
+        self.write(py, PyBytes::new_bound(py, &data).as_any())
+    }
+
+    pub fn write_eof(&self) {
+        self.send(WriteMsg::Eof);
+    }
+
+    pub fn can_write_eof(&self) -> bool {
+        true
+    }
+
+    pub fn get_write_buffer_size(&self) -> usize {
+        self.state.buffered.load(Ordering::SeqCst)
+    }
+
+    pub fn get_write_buffer_limits(&self) -> (usize, usize) {
+        (LOW_WATER, HIGH_WATER)
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.state.closing.load(Ordering::SeqCst)
+    }
+
+    pub fn close(&self) {
+        if !self.state.closing.swap(true, Ordering::SeqCst) {
+            self.send(WriteMsg::Close);
+        }
+    }
+
+    pub fn abort(&self) {
+        self.state.closing.store(true, Ordering::SeqCst);
+        self.state.closed.send_replace(true);
+        self.send(WriteMsg::Close);
+    }
+
+    pub fn is_reading(&self) -> bool {
+        !*self.state.reading_paused.borrow()
+    }
+
+    pub fn pause_reading(&self) {
+        self.state.reading_paused.send_replace(true);
+    }
+
+    pub fn resume_reading(&self) {
+        self.state.reading_paused.send_replace(false);
+    }
+
+    pub fn get_protocol(&self, py: Python) -> PyObject {
+        self.protocol.clone_ref(py)
+    }
+
+    pub fn set_protocol(&mut self, protocol: PyObject) {
+        self.protocol = protocol;
+    }
+
+    #[pyo3(signature = (name, default=None))]
+    pub fn get_extra_info(&self, py: Python, name: &str, default: Option<PyObject>) -> PyObject {
+        match name {
+            "peername" => match self.conn.remote_addr().parse::<SocketAddr>() {
+                Ok(addr) => (addr.ip().to_string(), addr.port()).into_py(py),
+                Err(_) => self.conn.remote_addr().into_py(py),
+            },
+            "ngrok_proto" => self.conn.proto().into_py(py),
+            _ => default.unwrap_or_else(|| py.None()),
+        }
+    }
+}
+
+impl Drop for StreamTransport {
+    fn drop(&mut self) {
+        debug!("StreamTransport drop");
+    }
+}
+
+/// Schedule a call on the event loop from any thread.
+fn call_soon(
+    py: Python,
+    event_loop: &PyObject,
+    callback: impl IntoPy<PyObject>,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<()> {
+    let mut call_args = vec![callback.into_py(py)];
+    call_args.extend(args.into_py(py).bind(py).iter().map(|a| a.unbind()));
+    event_loop.call_method1(
+        py,
+        intern!(py, "call_soon_threadsafe"),
+        PyTuple::new_bound(py, call_args),
+    )?;
+    Ok(())
+}
+
+/// Call a protocol method on the event loop thread, logging if the loop has gone away.
+fn protocol_call(
+    event_loop: &PyObject,
+    protocol: &PyObject,
+    method: &str,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> bool {
+    Python::with_gil(|py| {
+        let res = protocol
+            .getattr(py, method)
+            .and_then(|m| call_soon(py, event_loop, m, args));
+        if let Err(e) = &res {
+            debug!("Error scheduling {method}: {e:?}");
+        }
+        res.is_ok()
+    })
+}
+
+/// Feed connection data to the protocol, honoring pause_reading and close.
+async fn read_loop(
+    conn: Connection,
+    tx: mpsc::UnboundedSender<WriteMsg>,
+    state: Arc<TransportState>,
+    event_loop: PyObject,
+    protocol: PyObject,
+    transport: Py<StreamTransport>,
+) {
+    let mut paused = state.reading_paused.subscribe();
+    let mut closed = state.closed.subscribe();
+    let reader = conn.reader();
+    let mut reader = reader.lock().await;
+    let mut buf = vec![0; READ_CHUNK];
+    loop {
+        while *paused.borrow_and_update() {
+            tokio::select! {
+                _ = paused.changed() => {},
+                _ = closed.wait_for(|c| *c) => return,
+            }
+        }
+        let res = tokio::select! {
+            res = reader.read(&mut buf) => res,
+            _ = closed.wait_for(|c| *c) => return,
+        };
+        match res {
+            Ok(0) => {
+                schedule_eof(&event_loop, &protocol, &transport);
+                return;
+            }
+            Ok(n) => {
+                let data = Python::with_gil(|py| PyBytes::new_bound(py, &buf[..n]).unbind());
+                if !protocol_call(&event_loop, &protocol, "data_received", (data,)) {
+                    return;
+                }
+            }
+            Err(e) => {
+                debug!("Stream read error: {e:?}");
+                // the write loop ends the transport, reporting the error to connection_lost
+                state.closing.store(true, Ordering::SeqCst);
+                if tx.send(WriteMsg::Failed(e)).is_err() {
+                    debug!("Stream write loop already closed");
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Drain queued writes to the connection, resuming the protocol as the buffer empties.
+async fn write_loop(
+    conn: Connection,
+    mut rx: mpsc::UnboundedReceiver<WriteMsg>,
+    state: Arc<TransportState>,
+    event_loop: PyObject,
+    protocol: PyObject,
+    transport: Py<StreamTransport>,
+) {
+    let writer = conn.writer();
+    let mut writer = writer.lock().await;
+    let mut error = None;
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            WriteMsg::Data(data) => {
+                if let Err(e) = writer.write_all(&data).await {
+                    error = Some(e);
+                    break;
+                }
+                let buffered = state.buffered.fetch_sub(data.len(), Ordering::SeqCst) - data.len();
+                if buffered <= LOW_WATER && state.writing_paused.load(Ordering::SeqCst) {
+                    schedule_resume(&event_loop, &protocol, &transport);
+                }
+            }
+            WriteMsg::Eof => {
+                if let Err(e) = writer.shutdown().await {
+                    error = Some(e);
+                    break;
+                }
+            }
+            WriteMsg::Close => {
+                let _ = writer.shutdown().await;
+                break;
+            }
+            WriteMsg::Failed(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+    state.closing.store(true, Ordering::SeqCst);
+    state.closed.send_replace(true);
+    Python::with_gil(|py| {
+        let exc = error.map(|e| pyo3::PyErr::from(e).into_value(py).into_any());
+        let exc = exc.unwrap_or_else(|| py.None());
+        protocol_call(&event_loop, &protocol, "connection_lost", (exc,));
+    });
+}
+
+/// Resume writing on the loop thread, where write() makes the matching pause decision.
+fn schedule_resume(event_loop: &PyObject, protocol: &PyObject, transport: &Py<StreamTransport>) {
+    Python::with_gil(|py| {
+        let protocol = protocol.clone_ref(py);
+        let transport = transport.clone_ref(py);
+        let res = PyCFunction::new_closure_bound(py, None, None, move |args, _kwargs| {
+            let py = args.py();
+            let t = transport.borrow(py);
+            if t.state.buffered.load(Ordering::SeqCst) <= LOW_WATER
+                && t.state.writing_paused.swap(false, Ordering::SeqCst)
+            {
+                protocol.call_method0(py, intern!(py, "resume_writing"))?;
+            }
+            Ok::<_, pyo3::PyErr>(())
+        })
+        .and_then(|f| call_soon(py, event_loop, f, ()));
+        if let Err(e) = res {
+            debug!("Error scheduling resume_writing: {e:?}");
+        }
+    });
+}
+
+/// Signal EOF on the loop thread, closing the transport unless the protocol keeps it half-open.
+fn schedule_eof(event_loop: &PyObject, protocol: &PyObject, transport: &Py<StreamTransport>) {
+    Python::with_gil(|py| {
+        let protocol = protocol.clone_ref(py);
+        let transport = transport.clone_ref(py);
+        let res = PyCFunction::new_closure_bound(py, None, None, move |args, _kwargs| {
+            let py = args.py();
+            let keep_open = protocol
+                .call_method0(py, intern!(py, "eof_received"))?
+                .is_truthy(py)?;
+            if !keep_open {
+                transport.borrow(py).close();
+            }
+            Ok::<_, pyo3::PyErr>(())
+        })
+        .and_then(|f| call_soon(py, event_loop, f, ()));
+        if let Err(e) = res {
+            debug!("Error scheduling eof_received: {e:?}");
+        }
+    });
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use pyo3::{
+    pyclass,
+    pymethods,
+    types::PyDict,
+    Bound,
+    PyAny,
+    PyRef,
+    PyResult,
+    Python,
+};
+
+use crate::{
+    listener::{
+        drain_duration,
+        list_listeners,
+        Listener,
+    },
+    listener_builder::{
+        HttpListenerBuilder,
+        LabeledListenerBuilder,
+        TcpListenerBuilder,
+        TlsListenerBuilder,
+    },
+    session::Session,
+    wrapper::{
+        self,
+        block_on_timeout,
+    },
+};
+
+/// A blocking view of an ngrok session, for code without an asyncio loop. Each call
+/// releases the GIL and waits on the ngrok runtime, raising a :class:`TimeoutError` if
+/// a timeout is given and passes first. A listener which starts after its
+/// ``listen_sync`` timed out is closed.
+///
+/// Connect one with :meth:`SessionBuilder.connect_sync`, or wrap an existing
+/// :class:`Session`.
+#[pyclass]
+#[derive(Clone)]
+pub(crate) struct SyncSession {
+    session: Session,
+}
+
+impl From<Session> for SyncSession {
+    fn from(session: Session) -> Self {
+        SyncSession { session }
+    }
+}
+
+#[pymethods]
+impl SyncSession {
+    #[new]
+    fn new(session: Session) -> Self {
+        session.into()
+    }
+
+    fn __str__(&self) -> String {
+        "ngrok_sync_session".to_string()
+    }
+
+    /// The underlying :class:`Session`, for use with the async API.
+    #[getter]
+    pub fn session(&self) -> Session {
+        self.session.clone()
+    }
+
+    /// The state of the session: "connecting", "connected", "reconnecting" or "closed".
+    #[getter]
+    pub fn state(&self) -> &'static str {
+        self.session.state()
+    }
+
+    /// Start building a Listener backing an HTTP endpoint.
+    pub fn http_endpoint(&self) -> HttpListenerBuilder {
+        self.session.http_endpoint()
+    }
+
+    /// Start building a Listener backing a TCP endpoint.
+    pub fn tcp_endpoint(&self) -> TcpListenerBuilder {
+        self.session.tcp_endpoint()
+    }
+
+    /// Start building a Listener backing a TLS endpoint.
+    pub fn tls_endpoint(&self) -> TlsListenerBuilder {
+        self.session.tls_endpoint()
+    }
+
+    /// Start building a labeled Listener.
+    pub fn labeled_listener(&self) -> LabeledListenerBuilder {
+        self.session.labeled_listener()
+    }
+
+    /// Retrieve a list of this session's non-closed Listeners, in no particular order.
+    ///
+    /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+    #[pyo3(signature = (timeout=None))]
+    pub fn get_listeners(&self, py: Python, timeout: Option<f64>) -> PyResult<Vec<SyncListener>> {
+        let listing = list_listeners(Some(self.session.id()));
+        let listeners = block_on_timeout(py, timeout, "get_listeners", listing)?;
+        Ok(listeners.into_iter().map(SyncListener::from).collect())
+    }
+
+    /// Close a listener with the given ID.
+    ///
+    /// :param str id: The ID of the listener to close.
+    /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+    #[pyo3(signature = (id, timeout=None))]
+    pub fn close_listener(&self, py: Python, id: String, timeout: Option<f64>) -> PyResult<()> {
+        let session = self.session.clone();
+        block_on_timeout(py, timeout, "close_listener", async move {
+            session.async_close_listener(id).await
+        })
+    }
+
+    /// Close the ngrok session, and with it all of its listeners.
+    ///
+    /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+    #[pyo3(signature = (timeout=None))]
+    pub fn close(&self, py: Python, timeout: Option<f64>) -> PyResult<()> {
+        let session = self.session.clone();
+        block_on_timeout(
+            py,
+            timeout,
+            "close",
+            async move { session.async_close().await },
+        )
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Close the session, and with it all of its listeners, on leaving a ``with`` block.
+    fn __exit__(
+        &self,
+        py: Python,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<bool> {
+        let session = self.session.clone();
+        wrapper::block_on(py, async move { session.close_if_open().await }).map(|_| false)
+    }
+}
+
+/// A blocking view of a listener, for code without an asyncio loop. Start one with
+/// ``listen_sync`` or ``listen_and_forward_sync`` on a listener builder, or wrap an
+/// existing :class:`Listener`.
+#[pyclass]
+#[derive(Clone)]
+pub(crate) struct SyncListener {
+    listener: Listener,
+}
+
+impl From<Listener> for SyncListener {
+    fn from(listener: Listener) -> Self {
+        SyncListener { listener }
+    }
+}
+
+#[pymethods]
+impl SyncListener {
+    #[new]
+    fn new(listener: Listener) -> Self {
+        listener.into()
+    }
+
+    /// The underlying :class:`Listener`, for use with the async API.
+    #[getter]
+    pub fn listener(&self) -> Listener {
+        self.listener.clone()
+    }
+
+    /// Returns a listener's unique ID.
+    pub fn id(&self) -> String {
+        self.listener.id()
+    }
+
+    /// The URL that this listener backs.
+    pub fn url(&self) -> Option<String> {
+        self.listener.url()
+    }
+
+    /// The protocol of the endpoint that this listener backs.
+    pub fn proto(&self) -> Option<String> {
+        self.listener.proto()
+    }
+
+    /// The labels this listener was started with.
+    pub fn labels(&self) -> HashMap<String, String> {
+        self.listener.labels()
+    }
+
+    /// Returns a human-readable string presented in the ngrok dashboard and the API.
+    pub fn forwards_to(&self) -> String {
+        self.listener.forwards_to()
+    }
+
+    /// Returns the arbitrary metadata string for this listener.
+    pub fn metadata(&self) -> String {
+        self.listener.metadata()
+    }
+
+    /// Traffic counters for the connections this listener has forwarded, see :meth:`Listener.metrics`.
+    pub fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.listener.metrics(py)
+    }
+
+    /// Close the listener, as :meth:`Listener.close` does.
+    ///
+    /// :param float or None drain_timeout: The seconds to wait for forwarded connections to finish.
+    /// :param float or None timeout: The seconds to wait before raising a :class:`TimeoutError`.
+    #[pyo3(signature = (drain_timeout=None, timeout=None))]
+    pub fn close(
+        &self,
+        py: Python,
+        drain_timeout: Option<f64>,
+        timeout: Option<f64>,
+    ) -> PyResult<()> {
+        let drain_timeout = drain_timeout.map(drain_duration).transpose()?;
+        let listener = self.listener.clone();
+        block_on_timeout(py, timeout, "close", async move {
+            listener.async_close_drain(drain_timeout).await
+        })
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Close the listener on leaving a ``with`` block.
+    fn __exit__(
+        &self,
+        py: Python,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<bool> {
+        let listener = self.listener.clone();
+        wrapper::block_on(py, async move { listener.close_if_open().await }).map(|_| false)
+    }
+}
@@ -13,6 +13,7 @@ impl TcpListenerBuilder {
     ///
     /// .. _ngrok dashboard: https://dashboard.ngrok.com/cloud-edge/tcp-addresses
     pub fn remote_addr(self_: PyRefMut<Self>, remote_addr: String) -> PyRefMut<Self> {
+        self_.record("remote_addr", remote_addr.as_str());
         self_.set(|b| {
             b.remote_addr(remote_addr);
         });
@@ -21,6 +22,7 @@ impl TcpListenerBuilder {
 
     /// Enable endpoint pooling for this listener.
     pub fn pooling_enabled(self_: PyRefMut<Self>, pooling_enabled: bool) -> PyRefMut<Self> {
+        self_.record("pooling_enabled", pooling_enabled);
         self_.set(|b| {
             b.pooling_enabled(pooling_enabled);
         });
@@ -16,6 +16,7 @@ impl TlsListenerBuilder {
     ///
     /// .. _ngrok dashboard: https://dashboard.ngrok.com/cloud-edge/domains    
     pub fn domain(self_: PyRefMut<Self>, domain: String) -> PyRefMut<Self> {
+        self_.record("domain", domain.as_str());
         self_.set(|b| {
             b.domain(domain);
         });
@@ -29,6 +30,10 @@ impl TlsListenerBuilder {
         self_: PyRefMut<'a, Self>,
         mutual_tlsca: &PyByteArray,
     ) -> PyRefMut<'a, Self> {
+        self_.record_push(
+            "mutual_tls_cas",
+            String::from_utf8_lossy(&mutual_tlsca.to_vec()),
+        );
         self_.set(|b| {
             b.mutual_tlsca(Bytes::from(mutual_tlsca.to_vec()));
         });
@@ -37,6 +42,7 @@ impl TlsListenerBuilder {
 
     /// Enable endpoint pooling for this listener.
     pub fn pooling_enabled(self_: PyRefMut<Self>, endpoint_pooling: bool) -> PyRefMut<Self> {
+        self_.record("pooling_enabled", endpoint_pooling);
         self_.set(|b| {
             b.pooling_enabled(endpoint_pooling);
         });
@@ -52,12 +58,15 @@ impl TlsListenerBuilder {
         cert_pem: &PyByteArray,
         key_pem: &PyByteArray,
     ) -> PyRefMut<'a, Self> {
+        self_.record("crt", String::from_utf8_lossy(&cert_pem.to_vec()));
+        self_.record("key", String::from_utf8_lossy(&key_pem.to_vec()));
         self_.set(|b| {
             b.termination(
                 Bytes::from(cert_pem.to_vec()),
                 Bytes::from(key_pem.to_vec()),
             );
         });
+        self_.set_upstream(|o| o.tls_termination = true);
         self_
     }
 }
@@ -0,0 +1,766 @@
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+    task::{
+        Context,
+        Poll,
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use futures::prelude::*;
+use ngrok::{
+    prelude::*,
+    tunnel::AcceptError,
+};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex as SyncMutex;
+use pyo3::{
+    types::{
+        PyDict,
+        PyDictMethods,
+    },
+    Bound,
+    PyResult,
+    Python,
+};
+use rustls::{
+    pki_types::ServerName,
+    ClientConfig,
+    RootCertStore,
+};
+#[cfg(target_os = "windows")]
+use tokio::net::windows::named_pipe::ClientOptions;
+#[cfg(not(target_os = "windows"))]
+use tokio::net::UnixStream;
+use tokio::{
+    io::{
+        copy_bidirectional,
+        AsyncRead,
+        AsyncReadExt,
+        AsyncWrite,
+        AsyncWriteExt,
+        ReadBuf,
+    },
+    net::TcpStream,
+    sync::Notify,
+    task::{
+        AbortHandle,
+        JoinHandle,
+    },
+};
+use tokio_rustls::TlsConnector;
+use tracing::{
+    debug,
+    warn,
+};
+use url::Url;
+
+use crate::{
+    connection::ConnProto,
+    listener::ExtendedForwarder,
+    py_err,
+    ConfigError,
+};
+
+// proxy protocol v2 header signature
+const PROXY_V2_SIG: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+// longest possible proxy protocol v1 header, including the trailing CRLF
+const PROXY_V1_MAX: usize = 107;
+// largest request head read before answering with a gateway error
+const MAX_REQUEST_HEAD: usize = 64 * 1024;
+// how long an upstream which failed to accept a connection is passed over
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(10);
+
+pub(crate) trait IoStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IoStream for T {}
+
+/// Listener settings which change how connections are forwarded upstream. The ngrok
+/// tunnel does not expose these, so the listener builder records them as they are set.
+#[derive(Clone, Debug)]
+pub(crate) struct UpstreamOptions {
+    pub(crate) verify_upstream_tls: bool,
+    pub(crate) app_protocol: Option<String>,
+    pub(crate) proxy_proto: bool,
+    pub(crate) tls_termination: bool,
+}
+
+impl Default for UpstreamOptions {
+    fn default() -> Self {
+        UpstreamOptions {
+            verify_upstream_tls: true,
+            app_protocol: None,
+            proxy_proto: false,
+            tls_termination: false,
+        }
+    }
+}
+
+/// How a listener with several upstreams chooses one for each connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Strategy {
+    /// The first healthy upstream, in the order given.
+    Failover,
+    /// Each healthy upstream in turn.
+    RoundRobin,
+    /// The healthy upstream with the fewest active connections.
+    LeastConnections,
+}
+
+impl Strategy {
+    pub(crate) fn parse(strategy: &str) -> PyResult<Self> {
+        match strategy {
+            "failover" => Ok(Strategy::Failover),
+            "round_robin" => Ok(Strategy::RoundRobin),
+            "least_connections" => Ok(Strategy::LeastConnections),
+            _ => Err(py_err::<ConfigError>(format!(
+                "Unknown upstream strategy: {strategy:?}, expected \"failover\", \"round_robin\" or \"least_connections\""
+            ))),
+        }
+    }
+}
+
+/// An upstream service with passive health tracking: a failed connection attempt marks
+/// it unhealthy for a cooldown, and a successful one marks it healthy again.
+struct Upstream {
+    url: Url,
+    active: AtomicU64,
+    down_until: SyncMutex<Option<Instant>>,
+}
+
+impl Upstream {
+    fn healthy(&self) -> bool {
+        // a match rather than Option::is_none_or, which needs Rust 1.82
+        match *self.down_until.lock() {
+            Some(until) => Instant::now() >= until,
+            None => true,
+        }
+    }
+
+    fn failed(&self) {
+        self.down_until
+            .lock()
+            .replace(Instant::now() + UNHEALTHY_COOLDOWN);
+    }
+
+    fn succeeded(&self) {
+        self.down_until.lock().take();
+    }
+
+    /// Count a connection to this upstream, which stays active until the guard drops,
+    /// even if its forwarding task is aborted.
+    fn connected(&self) -> UpstreamGuard<'_> {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        UpstreamGuard(self)
+    }
+}
+
+/// Marks a connection to an upstream as active until dropped.
+struct UpstreamGuard<'a>(&'a Upstream);
+
+impl Drop for UpstreamGuard<'_> {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The upstream services a listener forwards to.
+pub(crate) struct UpstreamPool {
+    upstreams: Vec<Upstream>,
+    strategy: Strategy,
+    next: AtomicUsize,
+}
+
+impl UpstreamPool {
+    pub(crate) fn new(urls: Vec<Url>, strategy: Strategy) -> Self {
+        UpstreamPool {
+            upstreams: urls
+                .into_iter()
+                .map(|url| Upstream {
+                    url,
+                    active: AtomicU64::new(0),
+                    down_until: SyncMutex::new(None),
+                })
+                .collect(),
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn single(url: Url) -> Self {
+        Self::new(vec![url], Strategy::Failover)
+    }
+
+    /// The upstreams to try for a new connection, in order of preference. Unhealthy
+    /// upstreams come last, so they are only tried once every healthy one has failed.
+    fn candidates(&self) -> Vec<&Upstream> {
+        let len = self.upstreams.len();
+        let mut order: Vec<&Upstream> = match self.strategy {
+            Strategy::Failover => self.upstreams.iter().collect(),
+            Strategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % len.max(1);
+                self.upstreams
+                    .iter()
+                    .cycle()
+                    .skip(start)
+                    .take(len)
+                    .collect()
+            }
+            Strategy::LeastConnections => {
+                let mut order: Vec<&Upstream> = self.upstreams.iter().collect();
+                order.sort_by_key(|u| u.active.load(Ordering::Relaxed));
+                order
+            }
+        };
+        // stable, so the strategy's order holds within the healthy and unhealthy groups
+        order.sort_by_key(|u| !u.healthy());
+        order
+    }
+}
+
+/// Traffic counters for the connections a listener forwards, and the tasks forwarding them.
+#[derive(Debug, Default)]
+pub(crate) struct ListenerMetrics {
+    accepted: AtomicU64,
+    active: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    errors: AtomicU64,
+    // unix time in milliseconds, zero before the first connection
+    last_connection: AtomicU64,
+    // woken when the last active connection finishes
+    idle: Notify,
+    tasks: SyncMutex<Vec<AbortHandle>>,
+}
+
+impl ListenerMetrics {
+    /// Count a newly accepted connection, which stays active until the guard drops.
+    fn accepted(self: &Arc<Self>) -> ActiveGuard {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.last_connection.store(now, Ordering::Relaxed);
+        ActiveGuard(self.clone())
+    }
+
+    fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Keep hold of a connection's forwarding task, so it can be aborted.
+    fn track(&self, task: AbortHandle) {
+        let mut tasks = self.tasks.lock();
+        tasks.retain(|t| !t.is_finished());
+        tasks.push(task);
+    }
+
+    /// Wait for the active connections to finish.
+    pub(crate) async fn idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            // register before checking, so a connection finishing in between isn't missed
+            notified.as_mut().enable();
+            if self.active_count() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Cut any connections still being forwarded.
+    pub(crate) fn abort(&self) {
+        for task in self.tasks.lock().drain(..) {
+            task.abort();
+        }
+    }
+
+    pub(crate) fn accepted_count(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn active_count(&self) -> u64 {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn error_count(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// The metrics as a python dictionary.
+    pub(crate) fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("accepted", self.accepted_count())?;
+        dict.set_item("active", self.active_count())?;
+        dict.set_item("bytes_in", self.bytes_in())?;
+        dict.set_item("bytes_out", self.bytes_out())?;
+        dict.set_item("forward_errors", self.error_count())?;
+        let last = self.last_connection.load(Ordering::Relaxed);
+        dict.set_item("last_connection", (last > 0).then(|| last as f64 / 1000.0))?;
+        Ok(dict)
+    }
+}
+
+/// Marks a forwarded connection as active until dropped.
+struct ActiveGuard(Arc<ListenerMetrics>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// A stream which counts the bytes read from and written to it.
+struct Metered<S> {
+    inner: S,
+    metrics: Arc<ListenerMetrics>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Metered<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        self.metrics.bytes_in.fetch_add(read, Ordering::Relaxed);
+        res
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Metered<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            self.metrics
+                .bytes_out
+                .fetch_add(n as u64, Ordering::Relaxed);
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// The forwarding task of a listener created with listen_and_forward.
+pub(crate) struct UpstreamForwarder {
+    join: JoinHandle<Result<(), Box<dyn StdError + Send + Sync>>>,
+}
+
+impl UpstreamForwarder {
+    /// Forward the tunnel's connections in a background task.
+    pub(crate) fn spawn<T, C>(
+        mut tun: T,
+        pool: Arc<UpstreamPool>,
+        options: UpstreamOptions,
+        metrics: Arc<ListenerMetrics>,
+    ) -> Self
+    where
+        T: Stream<Item = Result<C, AcceptError>> + Unpin + Send + 'static,
+        C: Conn + ConnProto,
+    {
+        let join = tokio::spawn(async move {
+            forward_tunnel(&mut tun, pool, options, metrics)
+                .await
+                .map_err(|e| e.into())
+        });
+        UpstreamForwarder { join }
+    }
+}
+
+impl ExtendedForwarder for UpstreamForwarder {
+    fn get_join(&mut self) -> &mut JoinHandle<Result<(), Box<dyn StdError + Send + Sync>>> {
+        &mut self.join
+    }
+}
+
+/// Forward each connection accepted from the tunnel to the upstream pool, until the
+/// tunnel closes.
+///
+/// This follows the forwarding in ngrok-rs's `tunnel_ext.rs` (as of ngrok 0.15), which is
+/// private to that crate and only accepts its own connection types, so it can't be reused
+/// with metered connections or a pool of upstreams. Keep the proxy protocol, upstream TLS
+/// and bad gateway handling here in step with it.
+pub(crate) async fn forward_tunnel<T, C>(
+    tun: &mut T,
+    pool: Arc<UpstreamPool>,
+    options: UpstreamOptions,
+    metrics: Arc<ListenerMetrics>,
+) -> Result<(), io::Error>
+where
+    T: Stream<Item = Result<C, AcceptError>> + Unpin + Send + ?Sized,
+    C: Conn + ConnProto,
+{
+    while let Some(conn) = tun
+        .try_next()
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::NotConnected, err))?
+    {
+        // counted before spawning, so a close draining the listener can't miss it
+        let active = metrics.accepted();
+        let task = tokio::spawn(forward_conn(
+            conn,
+            pool.clone(),
+            options.clone(),
+            metrics.clone(),
+            active,
+        ));
+        metrics.track(task.abort_handle());
+    }
+    Ok(())
+}
+
+/// Forward a single connection to the first upstream service which accepts it.
+async fn forward_conn<C: Conn + ConnProto>(
+    conn: C,
+    pool: Arc<UpstreamPool>,
+    options: UpstreamOptions,
+    metrics: Arc<ListenerMetrics>,
+    _active: ActiveGuard,
+) {
+    let tunnel_tls = conn.tunnel_tls(options.tls_termination);
+    let proto_http = matches!(conn.conn_proto().as_str(), "http" | "https");
+    let mut stream = Metered {
+        inner: conn,
+        metrics: metrics.clone(),
+    };
+
+    let proxy_header = if options.proxy_proto {
+        match read_proxy_header(&mut stream).await {
+            Ok(header) => header,
+            Err(error) => {
+                metrics.error();
+                warn!(%error, "invalid proxy-protocol header");
+                return;
+            }
+        }
+    } else {
+        vec![]
+    };
+
+    let mut last_error = None;
+    let mut connected = None;
+    for upstream in pool.candidates() {
+        match connect(&upstream.url, &options, tunnel_tls, &proxy_header).await {
+            Ok(conn) => {
+                upstream.succeeded();
+                connected = Some((upstream, conn));
+                break;
+            }
+            Err(error) => {
+                warn!(%error, url = %upstream.url, "error connecting to upstream");
+                upstream.failed();
+                last_error = Some(error);
+            }
+        }
+    }
+    let Some((upstream, mut conn)) = connected else {
+        metrics.error();
+        if proto_http {
+            let error = last_error
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no upstreams"));
+            serve_gateway_error(stream, error).await;
+        }
+        return;
+    };
+
+    let _upstream_active = upstream.connected();
+    if let Err(error) = copy_bidirectional(&mut stream, &mut conn).await {
+        debug!(%error, "forwarded connection closed");
+    }
+}
+
+/// Read a proxy protocol header from the start of the stream, returning its raw bytes.
+async fn read_proxy_header(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let mut header = vec![0; PROXY_V2_SIG.len()];
+    stream.read_exact(&mut header).await?;
+    if header.as_slice() == PROXY_V2_SIG {
+        let mut rest = [0; 4];
+        stream.read_exact(&mut rest).await?;
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        header.extend_from_slice(&rest);
+        let start = header.len();
+        header.resize(start + len, 0);
+        stream.read_exact(&mut header[start..]).await?;
+        return Ok(header);
+    }
+    if !header.starts_with(b"PROXY ") {
+        return Err(invalid("missing proxy-protocol header"));
+    }
+    while !header.ends_with(b"\r\n") {
+        if header.len() >= PROXY_V1_MAX {
+            return Err(invalid("proxy-protocol v1 header too long"));
+        }
+        header.push(stream.read_u8().await?);
+    }
+    Ok(header)
+}
+
+/// Connect to the upstream service at the url. The proxy protocol header, if any, is
+/// written before any TLS handshake with the upstream.
+pub(crate) async fn connect(
+    url: &Url,
+    options: &UpstreamOptions,
+    tunnel_tls: bool,
+    proxy_header: &[u8],
+) -> io::Result<Box<dyn IoStream>> {
+    let host = url.host_str().unwrap_or("localhost");
+    let mut backend_tls = false;
+    let mut conn: Box<dyn IoStream> = match url.scheme() {
+        "tcp" => {
+            let port = url.port().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("missing port for tcp forwarding url {url}"),
+                )
+            })?;
+            Box::new(TcpStream::connect((host, port)).await?)
+        }
+        "http" => Box::new(TcpStream::connect((host, url.port().unwrap_or(80))).await?),
+        "https" | "tls" => {
+            backend_tls = true;
+            Box::new(TcpStream::connect((host, url.port().unwrap_or(443))).await?)
+        }
+        #[cfg(not(target_os = "windows"))]
+        "unix" => {
+            // a host is the first segment of a relative path
+            let addr = match url.host_str() {
+                Some(host) => format!("{host}{}", url.path()),
+                None => url.path().to_string(),
+            };
+            Box::new(UnixStream::connect(addr).await?)
+        }
+        #[cfg(target_os = "windows")]
+        "pipe" => Box::new(connect_pipe(url).await?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unrecognized scheme in forwarding url: {url}"),
+            ))
+        }
+    };
+
+    if !proxy_header.is_empty() {
+        conn.write_all(proxy_header).await?;
+    }
+
+    if backend_tls && !tunnel_tls {
+        let domain = ServerName::try_from(host.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let config = tls_config(options.app_protocol.as_deref(), options.verify_upstream_tls)?;
+        conn = Box::new(TlsConnector::from(config).connect(domain, conn).await?);
+    }
+
+    Ok(conn)
+}
+
+#[cfg(target_os = "windows")]
+async fn connect_pipe(url: &Url) -> io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    // the windows error for a pipe with no free instances
+    const ERROR_PIPE_BUSY: i32 = 231;
+    let mut pipe_name = url.path();
+    if url.host_str().is_some() {
+        pipe_name = pipe_name.strip_prefix('/').unwrap_or(pipe_name);
+    }
+    if pipe_name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("missing pipe name in forwarding url {url}"),
+        ));
+    }
+    let host = url
+        .host_str()
+        .map(|h| if h == "localhost" { "." } else { h })
+        .unwrap_or(".");
+    let addr = format!("\\\\{host}\\pipe\\{pipe_name}");
+    loop {
+        match ClientOptions::new().open(&addr) {
+            Ok(client) => return Ok(client),
+            Err(error) if error.raw_os_error() == Some(ERROR_PIPE_BUSY) => (),
+            Err(error) => return Err(error),
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// The client config for TLS to an upstream, one per combination of settings. Verified
+/// connections trust the host's root certificates, as ngrok-rs does, while unverified ones
+/// don't need them, so they work even where the host's roots can't be loaded.
+fn tls_config(
+    app_protocol: Option<&str>,
+    verify_upstream_tls: bool,
+) -> io::Result<Arc<ClientConfig>> {
+    static ROOTS: Lazy<Result<RootCertStore, String>> = Lazy::new(|| {
+        let mut roots = RootCertStore::empty();
+        let certs = rustls_native_certs::load_native_certs().map_err(|e| e.to_string())?;
+        roots.add_parsable_certificates(certs);
+        Ok(roots)
+    });
+    // keyed by whether http2 is offered and whether the upstream certificate is verified
+    type Configs = HashMap<(bool, bool), Arc<ClientConfig>>;
+    static CONFIGS: Lazy<parking_lot::Mutex<Configs>> = Lazy::new(Default::default);
+
+    let http2 = app_protocol == Some("http2");
+    let mut configs = CONFIGS.lock();
+    if let Some(config) = configs.get(&(http2, verify_upstream_tls)) {
+        return Ok(config.clone());
+    }
+    let builder = ClientConfig::builder();
+    let mut config = if verify_upstream_tls {
+        let roots = ROOTS.as_ref().map_err(|e| io::Error::other(e.clone()))?;
+        builder.with_root_certificates(roots.clone())
+    } else {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(danger::NoCertificateVerification::new()))
+    }
+    .with_no_client_auth();
+    if http2 {
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    }
+    let config = Arc::new(config);
+    configs.insert((http2, verify_upstream_tls), config.clone());
+    Ok(config)
+}
+
+/// Answer an http request with a bad gateway response.
+async fn serve_gateway_error(mut stream: impl IoStream, error: io::Error) {
+    // read the request head so the client sees a response rather than a reset
+    let mut head = vec![];
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let body = format!("failed to dial backend: {error}");
+    let resp = format!(
+        "HTTP/1.1 502 Bad Gateway\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(resp.as_bytes()).await {
+        debug!("Error serving bad gateway: {e:?}");
+    }
+    let _ = stream.shutdown().await;
+}
+
+mod danger {
+    use rustls::{
+        client::danger::{
+            HandshakeSignatureValid,
+            ServerCertVerified,
+            ServerCertVerifier,
+        },
+        crypto::{
+            aws_lc_rs,
+            verify_tls12_signature,
+            verify_tls13_signature,
+            CryptoProvider,
+        },
+        pki_types::{
+            CertificateDer,
+            ServerName,
+            UnixTime,
+        },
+        DigitallySignedStruct,
+        Error,
+        SignatureScheme,
+    };
+
+    /// Accepts any upstream certificate, for listeners with verify_upstream_tls disabled.
+    #[derive(Debug)]
+    pub(super) struct NoCertificateVerification(CryptoProvider);
+
+    impl NoCertificateVerification {
+        pub(super) fn new() -> Self {
+            Self(aws_lc_rs::default_provider())
+        }
+    }
+
+    impl ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+}
@@ -1,11 +1,26 @@
+use std::{
+    future::Future,
+    time::Duration,
+};
+
+use futures::{
+    future,
+    FutureExt,
+};
 use pyo3::{
+    exceptions::{
+        PyRuntimeError,
+        PyTimeoutError,
+    },
     intern,
     pyfunction,
     types::{
+        PyAnyMethods,
         PyModule,
         PyString,
         PyTuple,
     },
+    Bound,
     IntoPy,
     Py,
     PyAny,
@@ -13,13 +28,21 @@ use pyo3::{
     PyResult,
     Python,
 };
+use tokio::sync::oneshot;
 
 use crate::{
+    backend,
     listener::{
+        Listener,
         TCP_PREFIX,
         UNIX_PREFIX,
     },
     py_err,
+    session::{
+        Session,
+        SessionBuilder,
+    },
+    ConfigError,
 };
 
 /// Create a path name to use for pipe forwarding.
@@ -54,7 +77,7 @@ def run(input=None):
 
 /// Create a default HTTP listener. Optionally pass in a connected Session to use.
 ///
-/// Returns the listener if no async loop is running, otherwise returns a Task to await with a listener result.
+/// Returns the listener if no async loop is running, otherwise returns an awaitable with a listener result.
 ///
 /// :param session: The Session to use to create the listener.
 /// :type session: Session or None
@@ -62,13 +85,13 @@ def run(input=None):
 /// :rtype: Listener
 #[pyfunction]
 #[pyo3(text_signature = "(session=None)")]
-pub fn default(py: Python, session: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
-    default_listener_with_return(py, session, "listener")
+pub fn default(py: Python, session: Option<Session>) -> PyResult<Py<PyAny>> {
+    bridge(py, default_listener(py, session)?)
 }
 
 /// Create a default HTTP listener and get its file descriptor. Optionally pass in a connected Session to use.
 ///
-/// Returns the file descriptor if no async loop is running, otherwise returns a Task to await with a file descriptor result.
+/// Returns the file descriptor if no async loop is running, otherwise returns an awaitable with a file descriptor result.
 ///
 /// :param session: The Session to use to create the listener.
 /// :type session: Session or None
@@ -76,13 +99,17 @@ pub fn default(py: Python, session: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
 /// :rtype: int
 #[pyfunction]
 #[pyo3(text_signature = "(session=None)")]
-pub fn fd(py: Python, session: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
-    default_listener_with_return(py, session, "listener.fd")
+pub fn fd(py: Python, session: Option<Session>) -> PyResult<Py<PyAny>> {
+    let listening = default_listener(py, session)?;
+    bridge(py, async move {
+        let listener = listening.await?;
+        Python::with_gil(|py| listener.get_fd(py))
+    })
 }
 
 /// Create a default HTTP listener and get its socket name. Optionally pass in a connected Session to use.
 ///
-/// Returns the socket name if no async loop is running, otherwise returns a Task to await with a socket name result.
+/// Returns the socket name if no async loop is running, otherwise returns an awaitable with a socket name result.
 ///
 /// :param session: The Session to use to create the listener.
 /// :type session: Session or None
@@ -90,27 +117,52 @@ pub fn fd(py: Python, session: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
 /// :rtype: str
 #[pyfunction]
 #[pyo3(text_signature = "(session=None)")]
-pub fn getsockname(py: Python, session: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
-    default_listener_with_return(py, session, "listener.getsockname()")
+pub fn getsockname(py: Python, session: Option<Session>) -> PyResult<Py<PyAny>> {
+    let listening = default_listener(py, session)?;
+    bridge(py, async move {
+        let listener = listening.await?;
+        Python::with_gil(|py| listener.getsockname(py))
+    })
 }
 
-fn default_listener_with_return(
+/// Start an HTTP listener on the given session, or on a new one authenticated from the
+/// environment.
+fn default_listener(
     py: Python,
-    session: Option<Py<PyAny>>,
-    return_str: &str,
-) -> PyResult<Py<PyAny>> {
-    loop_wrap(
-        py,
-        session,
-        &format!(
-            r###"
-    if input is None:
-        input = await SessionBuilder().authtoken_from_env().connect()
-    listener = await input.http_endpoint().listen()
-    return {return_str}
-    "###
-        ),
-    )
+    session: Option<Session>,
+) -> PyResult<impl Future<Output = PyResult<Listener>> + Send + 'static> {
+    let connecting = match session {
+        Some(session) => future::ready(Ok(session)).boxed(),
+        None => {
+            let builder = Bound::new(py, SessionBuilder::new())?;
+            SessionBuilder::authtoken_from_env(builder.borrow_mut());
+            let connecting = builder.borrow().connecting();
+            connecting.boxed()
+        }
+    };
+    Ok(async move {
+        let session = connecting.await?;
+        session.http_endpoint().async_listen().await
+    })
+}
+
+/// The given listener, or a new default HTTP listener.
+fn listener_or_default(
+    py: Python,
+    listener: Option<Py<Listener>>,
+) -> PyResult<impl Future<Output = PyResult<Py<Listener>>> + Send + 'static> {
+    let listening = match listener {
+        Some(listener) => future::ready(Ok(listener)).boxed(),
+        None => {
+            let listening = default_listener(py, None)?;
+            async move {
+                let listener = listening.await?;
+                Python::with_gil(|py| Py::new(py, listener))
+            }
+            .boxed()
+        }
+    };
+    Ok(listening)
 }
 
 /// Create and return a listening default HTTP listener.
@@ -118,40 +170,32 @@ fn default_listener_with_return(
 /// such as a http.server.HTTPServer, and the listener will
 /// forward TCP to that server_address. Optionally also pass in a previously created listener.
 ///
-/// Returns the created listener if no async loop is running, otherwise returns a Task to await with a listener result.
+/// Returns the created listener if no async loop is running, otherwise returns an awaitable with a listener result.
 ///
 /// :param server: The server to link with a listener.
 /// :type server: http.server.HTTPServer or None
 /// :param listener: The Listener to use to link with the server.
 /// :type listener: Listener or None
-/// :return: The listener linked with the server, or a Task to await for said listener.
-/// :rtype: Listener or Task
+/// :return: The listener linked with the server, or an awaitable for said listener.
+/// :rtype: Listener or Awaitable
 #[pyfunction]
 #[pyo3(text_signature = "(server=None, listener=None)")]
 pub fn listen(
     py: Python,
     server: Option<Py<PyAny>>,
-    listener: Option<Py<PyAny>>,
+    listener: Option<Py<Listener>>,
 ) -> PyResult<Py<PyAny>> {
-    let mut forward = "".to_string();
-    if let Some(server) = server {
-        let address = address_from_server(py, server)?;
-        forward = format!("input.forward('{address}')");
-    }
-
-    loop_wrap(
-        py,
-        listener,
-        &format!(
-            r###"
-    if input is None:
-        session = await SessionBuilder().authtoken_from_env().connect()
-        input = await session.http_endpoint().listen()
-    {forward}
-    return input
-    "###
-        ),
-    )
+    let address = server
+        .map(|server| address_from_server(py, server))
+        .transpose()?;
+    let listening = listener_or_default(py, listener)?;
+    bridge(py, async move {
+        let listener = listening.await?;
+        if let Some(address) = address {
+            Python::with_gil(|py| listener.borrow(py).spawn_forward(address))?;
+        }
+        Ok(listener)
+    })
 }
 
 pub(crate) fn address_from_server(py: Python, server: Py<PyAny>) -> Result<String, PyErr> {
@@ -174,7 +218,7 @@ pub(crate) fn address_from_server(py: Python, server: Py<PyAny>) -> Result<Strin
         let address: &PyString = server_address_attr.downcast(py)?;
         Ok(format!("{UNIX_PREFIX}{address}"))
     } else {
-        return Err(py_err(format!(
+        return Err(py_err::<ConfigError>(format!(
             "Unhandled server_address type: {address_type}"
         )));
     }
@@ -183,54 +227,109 @@ pub(crate) fn address_from_server(py: Python, server: Py<PyAny>) -> Result<Strin
 /// Set the WERKZEUG_SERVER_FD environment variable with a file descriptor from a default HTTP listener.
 /// Also sets WERKZEUG_RUN_MAIN to "true" to engage the use of WERKZEUG_SERVER_FD.
 ///
-/// Returns the created listener if no async loop is running, otherwise returns a Task to await with a listener result.
+/// Returns the created listener if no async loop is running, otherwise returns an awaitable with a listener result.
 ///
 /// :param listener: The Listener to use to link with the werkzeug server.
 /// :type listener: Listener or None
-/// :return: The listener linked with the server, or a Task to await for said listener.
-/// :rtype: Listener or Task
+/// :return: The listener linked with the server, or an awaitable for said listener.
+/// :rtype: Listener or Awaitable
 #[pyfunction]
 #[pyo3(text_signature = "(listener=None)")]
-pub fn werkzeug_develop(py: Python, listener: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
-    loop_wrap(
-        py,
-        listener,
-        r#"
-    if input is None:
-        session = await SessionBuilder().authtoken_from_env().connect()
-        input = await session.http_endpoint().listen()
-
-    import os
-    os.environ["WERKZEUG_SERVER_FD"] = str(input.fd)
-    os.environ["WERKZEUG_RUN_MAIN"] = "true"
-    return input
-    "#,
-    )
+pub fn werkzeug_develop(py: Python, listener: Option<Py<Listener>>) -> PyResult<Py<PyAny>> {
+    let listening = listener_or_default(py, listener)?;
+    bridge(py, async move {
+        let listener = listening.await?;
+        Python::with_gil(|py| -> PyResult<_> {
+            let fd = listener.borrow(py).get_fd(py)?;
+            let environ = PyModule::import_bound(py, "os")?.getattr("environ")?;
+            environ.set_item("WERKZEUG_SERVER_FD", fd.bind(py).str()?)?;
+            environ.set_item("WERKZEUG_RUN_MAIN", "true")?;
+            Ok(())
+        })?;
+        Ok(listener)
+    })
 }
 
-/// Python wrapper to call the passed in work in an async context whether or not an async loop is running.
-pub(crate) fn loop_wrap(py: Python, input: Option<Py<PyAny>>, work: &str) -> PyResult<Py<PyAny>> {
-    let code = format!(
-        r###"
-import asyncio
-import ngrok
-from ngrok import SessionBuilder
+/// Run a future to completion on the tokio runtime, releasing the GIL while it runs.
+pub(crate) fn block_on<F>(py: Python, fut: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    py.allow_threads(|| match tokio::runtime::Handle::try_current() {
+        // already on a runtime thread, such as in a handler called by a session task
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(fut)),
+        Err(_) => pyo3_async_runtimes::tokio::get_runtime().block_on(fut),
+    })
+}
 
-async def wrap(input=None):
-{work}
+/// Block on a future as `block_on` does, giving up with a :class:`TimeoutError` after the
+/// given seconds. What names the operation in the error message. With a timeout the future
+/// runs in its own task, so that giving up leaves it to finish rather than cutting it off
+/// part way through an RPC.
+pub(crate) fn block_on_timeout<F, T>(
+    py: Python,
+    timeout: Option<f64>,
+    what: &str,
+    fut: F,
+) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    block_on_timeout_cleanup(py, timeout, what, fut, |_| future::ready(()))
+}
 
-def run(input=None):
-    try:
-        running_loop = asyncio.get_running_loop()
-        return running_loop.create_task(wrap(input))
-    except RuntimeError:
-        pass
+/// Block on a future as `block_on_timeout` does, passing a result which arrives after the
+/// caller gave up to cleanup, such as to close a listener which nothing will own.
+pub(crate) fn block_on_timeout_cleanup<F, T, C, CF>(
+    py: Python,
+    timeout: Option<f64>,
+    what: &str,
+    fut: F,
+    cleanup: C,
+) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + 'static,
+    C: FnOnce(T) -> CF + Send + 'static,
+    CF: Future<Output = ()> + Send,
+{
+    let Some(secs) = timeout else {
+        return block_on(py, fut);
+    };
+    let duration = Duration::try_from_secs_f64(secs)
+        .map_err(|e| py_err::<ConfigError>(format!("invalid timeout {secs}, {e}")))?;
 
-    return asyncio.run(wrap(input))
-    "###
-    );
+    let (tx, rx) = oneshot::channel();
+    pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+        // the receiver is gone once the caller has given up
+        if let Err(Ok(value)) = tx.send(fut.await) {
+            cleanup(value).await;
+        }
+    });
+    match block_on(py, tokio::time::timeout(duration, rx)) {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(PyRuntimeError::new_err(format!("{what} was cancelled"))),
+        Err(_) => Err(PyTimeoutError::new_err(format!(
+            "{what} timed out after {secs}s"
+        ))),
+    }
+}
 
-    call_code(py, input, code.as_str())
+/// Run work on the tokio runtime for a caller which may or may not be in an async context.
+/// With an asyncio loop or a Trio run active in this thread this returns an awaitable for the
+/// result, otherwise it blocks until the work is done, with the GIL released, and returns the result.
+pub(crate) fn bridge<F, T>(py: Python, fut: F) -> PyResult<Py<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<Py<PyAny>> + Send + 'static,
+{
+    if backend::running(py) {
+        return Ok(backend::future_into_py(py, fut)?.unbind());
+    }
+    let value = block_on(py, fut)?;
+    Ok(value.into_py(py))
 }
 
 /// Call the given code, returning the required 'retval' attribute from it.
@@ -5,15 +5,27 @@ from http.server import HTTPServer, BaseHTTPRequestHandler
 from requests.adapters import HTTPAdapter
 from requests.packages.urllib3.util.retry import Retry
 import asyncio
+import contextvars
+import json
 import ngrok
 import os
 import random
 import requests
+import socket
 import socketserver
+import ssl
+import struct
+import tempfile
 import threading
+import time
 import unittest
 import logging
 
+try:
+    import trio
+except ImportError:
+    trio = None
+
 logging.basicConfig(level=logging.DEBUG)
 
 expected = "Hello"
@@ -38,6 +50,8 @@ class HelloHandler(BaseHTTPRequestHandler):
         if self.path == "/headers":
             if self.headers["foo"] != "bar" or self.headers["baz"] is not None:
                 code = 555
+        elif self.path == "/slow":
+            time.sleep(2)
 
         self.send_response(code)
         self.send_header("Content-Length", len(body))
@@ -73,6 +87,20 @@ def make_http(use_unix_socket=False):
     return server
 
 
+def make_https():
+    server = HTTPServer(("localhost", 0), HelloHandler)
+    # self-signed for rust-sdk.example.com, so only passes with verification disabled
+    context = ssl.SSLContext(ssl.PROTOCOL_TLS_SERVER)
+    context.load_cert_chain("examples/domain.crt", "examples/domain.key")
+    server.socket = context.wrap_socket(server.socket, server_side=True)
+    addr = server.server_address
+    server.listen_to = "{}:{}".format(addr[0], addr[1])
+
+    thread = threading.Thread(target=server.serve_forever, daemon=True)
+    thread.start()
+    return server
+
+
 async def make_session():
     return await ngrok.SessionBuilder().authtoken_from_env().connect()
 
@@ -193,6 +221,47 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
         self.assertEqual("added-header-value", response.headers["added-header"])
         await shutdown(listener, http_server)
 
+    async def test_https_listener_with_traffic_policy_object(self):
+        action = ngrok.PolicyAction(
+            "add-headers", {"headers": {"added-header": "added-header-value"}}
+        )
+        policy = ngrok.TrafficPolicy(
+            on_http_response=[ngrok.PolicyRule([action], name="add")]
+        )
+        self.assertEqual(policy, ngrok.TrafficPolicy.from_json(policy.to_json()))
+        self.assertEqual(policy, ngrok.TrafficPolicy.from_yaml(policy.to_yaml()))
+        self.assertEqual("add-headers", policy.on_http_response[0].actions[0].type)
+
+        # unknown types and fields are passed on for the service to check
+        self.assertEqual("new-action", ngrok.PolicyAction("new-action").type)
+        ngrok.PolicyAction("circuit-breaker")
+        newer = {
+            "on_http_request": [
+                {"actions": [{"type": "deny"}], "new_rule_field": True}
+            ],
+            "new_phase": [],
+        }
+        self.assertEqual(newer, ngrok.TrafficPolicy.from_dict(newer).to_dict())
+        with self.assertRaises(ngrok.ConfigError):
+            ngrok.PolicyAction("custom-response", {"content": "no status"})
+        with self.assertRaises(ngrok.ConfigError):
+            ngrok.TrafficPolicy(on_tcp_connect=[ngrok.PolicyRule([action])])
+
+        with tempfile.NamedTemporaryFile("w", suffix=".yml") as f:
+            f.write("on_http_request:\n  - actions:\n      - type: deny\n        confg: {}\n")
+            f.flush()
+            with self.assertRaises(ngrok.ConfigError) as ctx:
+                ngrok.TrafficPolicy.load(f.name)
+            self.assertIn(f.name, str(ctx.exception))
+            self.assertEqual(4, ctx.exception.line)
+
+        http_server, session = await make_http_and_session()
+        listener = await session.http_endpoint().traffic_policy(policy).listen()
+        listener.forward(http_server.listen_to)
+        response = retry_request().get(listener.url())
+        self.assertEqual("added-header-value", response.headers["added-header"])
+        await shutdown(listener, http_server)
+
     async def test_https_listener_with_invalid_policy_json(self):
         error = None
         try:
@@ -299,6 +368,193 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
         await self.validate_http_request(listener.url())
         await shutdown(listener, http_server)
 
+    async def test_listener_metrics(self):
+        http_server, session = await make_http_and_session()
+        listener = await session.http_endpoint().listen_and_serve(http_server)
+        metrics = listener.metrics()
+        self.assertEqual(0, metrics["accepted"])
+        self.assertIsNone(metrics["last_connection"])
+
+        await self.validate_http_request(listener.url())
+        metrics = listener.metrics()
+        self.assertTrue(metrics["accepted"] >= 1)
+        self.assertTrue(metrics["bytes_in"] > 0)
+        self.assertTrue(metrics["bytes_out"] > 0)
+        self.assertEqual(0, metrics["forward_errors"])
+        self.assertIsNotNone(metrics["last_connection"])
+        await shutdown(listener, http_server)
+
+    async def test_reconfigure(self):
+        http_server, session = await make_http_and_session()
+        listener = (
+            await session.http_endpoint()
+            .pooling_enabled(True)
+            .metadata("before")
+            .listen_and_serve(http_server)
+        )
+        await self.validate_http_request(listener.url())
+
+        replacement = await listener.reconfigure(
+            metadata="after", deny_cidr="0.0.0.0/0", allow_cidr="127.0.0.1/32"
+        )
+        self.assertEqual(listener.url(), replacement.url())
+        self.assertNotEqual(listener.id(), replacement.id())
+        self.assertEqual("after", replacement.metadata())
+        self.assertEqual(listener.forwards_to(), replacement.forwards_to())
+        ids = [l.id() for l in await session.get_listeners()]
+        self.assertNotIn(listener.id(), ids)
+        self.assertIn(replacement.id(), ids)
+
+        error = requests.get(replacement.url())
+        self.assertEqual(403, error.status_code)
+
+        # options which aren't given are kept from the listener being replaced
+        replacement = await replacement.reconfigure(metadata="again")
+        self.assertEqual("again", replacement.metadata())
+        self.assertEqual(403, requests.get(replacement.url()).status_code)
+
+        replacement = await replacement.reconfigure(allow_cidr=None, deny_cidr=None)
+        self.assertEqual("again", replacement.metadata())
+        await self.validate_http_request(replacement.url())
+
+        with self.assertRaises(ngrok.ConfigError):
+            await replacement.reconfigure(addr="localhost:9999")
+        with self.assertRaises(ngrok.ConfigError):
+            await replacement.reconfigure(remote_addr="1.tcp.ngrok.io:1234")
+        await shutdown(replacement, http_server)
+
+    async def test_reconfigure_unpooled(self):
+        http_server, session = await make_http_and_session()
+        listener = await session.http_endpoint().listen_and_serve(http_server)
+        with self.assertRaises(ngrok.ListenerError):
+            await listener.reconfigure(metadata="after")
+        await self.validate_http_request(listener.url())
+        await shutdown(listener, http_server)
+
+    async def test_close_drain(self):
+        http_server, session = await make_http_and_session()
+        listener = await session.http_endpoint().listen_and_serve(http_server)
+        slow = asyncio.create_task(
+            asyncio.to_thread(retry_request().get, listener.url() + "/slow")
+        )
+        for _ in range(100):
+            if listener.metrics()["active"] > 0:
+                break
+            await asyncio.sleep(0.1)
+        self.assertEqual(1, listener.metrics()["active"])
+
+        await listener.close(drain_timeout=10)
+        response = await slow
+        self.assertEqual(200, response.status_code)
+        self.assertEqual(expected, response.text)
+        self.assertEqual(0, listener.metrics()["active"])
+
+        with self.assertRaises(ngrok.ConfigError):
+            await listener.close(drain_timeout=-1)
+        http_server.shutdown()
+        http_server.server_close()
+
+    async def test_context_managers(self):
+        http_server = make_http()
+        async with await make_session() as session:
+            async with await session.http_endpoint().listen_and_serve(
+                http_server
+            ) as listener:
+                await self.validate_http_request(listener.url())
+            self.assertEqual([], await session.get_listeners())
+
+            with self.assertRaises(ValueError):
+                async with await session.tcp_endpoint().listen_and_serve(
+                    http_server
+                ) as listener:
+                    raise ValueError("closes anyway")
+            self.assertEqual([], await session.get_listeners())
+
+            with await session.http_endpoint().listen_and_serve(
+                http_server
+            ) as listener:
+                self.assertIsNotNone(listener.url())
+            self.assertEqual([], await session.get_listeners())
+
+            # left open for the session to clean up
+            listener = await session.http_endpoint().listen_and_serve(http_server)
+        self.assertEqual("closed", session.state)
+        self.assertNotIn(listener.id(), [l.id() for l in await ngrok.get_listeners()])
+        http_server.shutdown()
+        http_server.server_close()
+
+    def test_sync_api(self):
+        http_server = make_http()
+        builder = ngrok.SessionBuilder().authtoken_from_env()
+        with builder.connect_sync(timeout=30) as session:
+            self.assertIsInstance(session, ngrok.SyncSession)
+            self.assertEqual("connected", session.state)
+            listener = session.http_endpoint().listen_and_forward_sync(
+                http_server.listen_to, timeout=30
+            )
+            self.assertIsInstance(listener, ngrok.SyncListener)
+            response = retry_request().get(listener.url())
+            self.assertEqual(expected, response.text)
+            self.assertEqual(
+                [listener.id()], [l.id() for l in session.get_listeners()]
+            )
+            listener.close(timeout=30)
+            self.assertEqual([], session.get_listeners())
+
+            with session.tcp_endpoint().listen_sync() as listener:
+                self.assertTrue(listener.url().startswith("tcp://"))
+            self.assertEqual([], session.get_listeners())
+
+            with self.assertRaises(TimeoutError):
+                session.http_endpoint().listen_sync(timeout=0.000001)
+        self.assertEqual("closed", session.state)
+        http_server.shutdown()
+        http_server.server_close()
+
+    @unittest.skipUnless(trio, "trio is not installed")
+    def test_trio(self):
+        http_server = make_http()
+
+        async def run():
+            session = await make_session()
+            self.assertEqual("connected", session.state)
+            listener = await session.http_endpoint().listen_and_forward(
+                http_server.listen_to
+            )
+            response = await trio.to_thread.run_sync(
+                lambda: retry_request().get(listener.url())
+            )
+            self.assertEqual(expected, response.text)
+            self.assertEqual([listener.id()], [l.id() for l in await session.get_listeners()])
+
+            with trio.move_on_after(0.000001):
+                await session.http_endpoint().listen()
+            await listener.close()
+            self.assertEqual([], await session.get_listeners())
+            await session.close()
+            self.assertEqual("closed", session.state)
+
+        trio.run(run)
+        http_server.shutdown()
+        http_server.server_close()
+
+    async def test_serve_metrics(self):
+        http_server, session = await make_http_and_session()
+        listener = await session.http_endpoint().listen_and_serve(http_server)
+        await self.validate_http_request(listener.url())
+
+        addr = ngrok.serve_metrics("127.0.0.1:0")
+        response = requests.get(f"http://{addr}/metrics")
+        self.assertEqual(200, response.status_code)
+        self.assertTrue(
+            response.headers["content-type"].startswith("application/openmetrics-text")
+        )
+        self.assertTrue("ngrok_session_up{" in response.text)
+        self.assertTrue(f'listener_id="{listener.id()}"' in response.text)
+        self.assertTrue("ngrok_listener_connections_total" in response.text)
+        self.assertTrue(response.text.endswith("# EOF\n"))
+        await shutdown(listener, http_server)
+
     async def test_listen_and_serve_unix(self):
         http_server, session = await make_http_and_session(use_unix_socket=True)
         listener = await session.http_endpoint().listen_and_serve(http_server)
@@ -417,6 +673,101 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
 
         await shutdown(listener, tcp_server)
 
+    async def test_proxy_proto_forward(self):
+        class ProxyHandler(socketserver.StreamRequestHandler):
+            header = None
+            request_line = None
+
+            def handle(self):
+                if self.rfile.peek(1).startswith(b"PROXY"):
+                    header = self.rfile.readline()
+                else:
+                    header = self.rfile.read(16)
+                    header += self.rfile.read(int.from_bytes(header[14:16], "big"))
+                ProxyHandler.header = header
+                ProxyHandler.request_line = self.rfile.readline()
+                self.wfile.write(
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nHello"
+                )
+
+        tcp_server = socketserver.TCPServer(("localhost", 0), ProxyHandler)
+        thread = threading.Thread(target=tcp_server.serve_forever, daemon=True)
+        thread.start()
+        addr = tcp_server.server_address
+        session = await make_session()
+
+        for version, prefix in (("1", b"PROXY TCP"), ("2", b"\r\n\r\n\0\r\nQUIT\n")):
+            listener = (
+                await session.http_endpoint()
+                .proxy_proto(version)
+                .listen_and_forward("http://{}:{}".format(addr[0], addr[1]))
+            )
+            response = retry_request().get(listener.url())
+            self.assertEqual(expected, response.text)
+            # the header is passed on whole, with the request following it intact
+            self.assertTrue(ProxyHandler.header.startswith(prefix), ProxyHandler.header)
+            self.assertTrue(ProxyHandler.request_line.startswith(b"GET / HTTP/1.1"))
+            self.assertGreater(listener.metrics()["bytes_in"], 0)
+            await listener.close()
+
+        tcp_server.shutdown()
+        tcp_server.server_close()
+
+    async def test_upstream_tls_verify(self):
+        https_server = make_https()
+        session = await make_session()
+        listener = await session.http_endpoint().listen_and_forward(
+            "https://" + https_server.listen_to
+        )
+
+        # retry while the endpoint comes online, but not on the expected bad gateway
+        response = requests.get(listener.url())
+        for _ in range(5):
+            if response.status_code != 404:
+                break
+            time.sleep(1)
+            response = requests.get(listener.url())
+        self.assertEqual(502, response.status_code)
+        self.assertIn("failed to dial backend", response.text)
+        self.assertGreater(listener.metrics()["forward_errors"], 0)
+
+        no_verify = (
+            await session.http_endpoint()
+            .verify_upstream_tls(False)
+            .listen_and_forward("https://" + https_server.listen_to)
+        )
+        await self.validate_http_request(no_verify.url())
+        await listener.close()
+        await shutdown(no_verify, https_server)
+
+    async def test_tls_passthrough_and_termination(self):
+        https_server = make_https()
+        http_server, session = await make_http_and_session()
+
+        # without termination the client's TLS goes through to the upstream untouched
+        passthrough = await session.tls_endpoint().listen_and_forward(
+            "tls://" + https_server.listen_to
+        )
+        url = passthrough.url().replace("tls:", "https:")
+        response = retry_request().get(url, verify=False)
+        self.assertEqual(expected, response.text)
+
+        with open("examples/domain.crt", "rb") as crt, open(
+            "examples/domain.key", "rb"
+        ) as key:
+            cert, key = bytearray(crt.read()), bytearray(key.read())
+        terminated = (
+            await session.tls_endpoint()
+            .termination(cert, key)
+            .listen_and_forward(http_server.listen_to)
+        )
+        url = terminated.url().replace("tls:", "https:")
+        response = retry_request().get(url, verify=False)
+        self.assertEqual(expected, response.text)
+
+        await shutdown(passthrough, https_server)
+        await shutdown(terminated, http_server)
+
     async def test_ip_restriction_http(self):
         http_server, session = await make_http_and_session()
         error = await self.ip_restriction(http_server, session.http_endpoint())
@@ -491,6 +842,95 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
             http_server, listener, listener.url().replace("tcp:", "http:")
         )
 
+    async def test_accept_connections(self):
+        session = await make_session()
+        listener = await session.tcp_endpoint().listen()
+
+        async def echo():
+            async for conn in listener:
+                self.assertEqual("tcp", conn.proto())
+                self.assertIsNotNone(conn.remote_addr())
+                self.assertEqual(b"", await conn.read(0))
+                await conn.write(await conn.read(5))
+                await conn.drain()
+                await conn.close()
+                break
+
+        task = asyncio.create_task(echo())
+        host, port = listener.url().replace("tcp://", "").split(":")
+        reader, writer = await asyncio.open_connection(host, int(port))
+        writer.write(b"hello")
+        await writer.drain()
+        self.assertEqual(b"hello", await reader.readexactly(5))
+        writer.close()
+        await task
+
+        # closing the listener while iteration waits ends it
+        async def drain():
+            return [conn async for conn in listener]
+
+        task = asyncio.create_task(drain())
+        await asyncio.sleep(1)
+        await listener.close()
+        self.assertEqual([], await asyncio.wait_for(task, 10))
+
+    async def test_start_server(self):
+        session = await make_session()
+        served = asyncio.Event()
+
+        async def echo(reader, writer):
+            self.assertIsNotNone(writer.get_extra_info("peername"))
+            writer.write(await reader.readexactly(5))
+            await writer.drain()
+            writer.close()
+            await writer.wait_closed()
+            served.set()
+
+        listener = await ngrok.start_server(echo, await session.tcp_endpoint().listen())
+        host, port = listener.url().replace("tcp://", "").split(":")
+        reader, writer = await asyncio.open_connection(host, int(port))
+        writer.write(b"hello")
+        await writer.drain()
+        self.assertEqual(b"hello", await reader.readexactly(5))
+        writer.close()
+        await asyncio.wait_for(served.wait(), 10)
+        await listener.close()
+
+    async def test_start_server_reset(self):
+        session = await make_session()
+        done = asyncio.get_running_loop().create_future()
+
+        async def wait_for_data(reader, writer):
+            try:
+                done.set_result(await reader.read(100))
+            except OSError as e:
+                done.set_result(e)
+
+        listener = await ngrok.start_server(
+            wait_for_data, await session.tcp_endpoint().listen()
+        )
+        host, port = listener.url().replace("tcp://", "").split(":")
+        reader, writer = await asyncio.open_connection(host, int(port))
+        await asyncio.sleep(1)
+        # close with a zero linger timeout to reset rather than shut down the connection
+        writer.get_extra_info("socket").setsockopt(
+            socket.SOL_SOCKET, socket.SO_LINGER, struct.pack("ii", 1, 0)
+        )
+        writer.transport.abort()
+        result = await asyncio.wait_for(done, 10)
+        self.assertTrue(result == b"" or isinstance(result, OSError), result)
+        await listener.close()
+        await session.close()
+
+    async def test_session_events(self):
+        session = await make_session()
+        self.assertEqual("connected", session.state)
+        events = session.events()
+        await session.close()
+        self.assertEqual("closed", session.state)
+        kinds = [event.kind async for event in events]
+        self.assertEqual("closed", kinds[-1])
+
     async def test_tls_listener(self):
         http_server, session = await make_http_and_session()
         listener = (
@@ -577,6 +1017,15 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
         self.assertTrue("http" in listener.url())
         await session.close()
 
+    def test_default_blocking(self):
+        # with no running loop the listener is returned directly
+        listener = ngrok.default()
+        self.assertIsInstance(listener, ngrok.Listener)
+        self.assertTrue("http" in listener.url())
+        self.assertIn(listener.id(), [l.id() for l in ngrok.get_listeners()])
+        ngrok.disconnect(listener.url())
+        self.assertNotIn(listener.id(), [l.id() for l in ngrok.get_listeners()])
+
     async def test_getsockname(self):
         session = await make_session()
         sockname = await ngrok.getsockname(session)
@@ -612,6 +1061,27 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
         await listener3.close()
         await listener4.close()
 
+    async def test_listen_and_forward_upstreams(self):
+        http_server, session = await make_http_and_session()
+        dead = make_http()
+        dead_url = "http://" + dead.listen_to
+        dead.shutdown()
+        dead.server_close()
+        urls = [dead_url, "http://" + http_server.listen_to]
+
+        for strategy in ["failover", "round_robin", "least_connections"]:
+            listener = await session.http_endpoint().listen_and_forward(
+                urls, strategy=strategy
+            )
+            for _ in range(3):
+                await self.validate_http_request(listener.url())
+            await listener.close()
+
+        with self.assertRaises(ngrok.ConfigError):
+            await session.http_endpoint().listen_and_forward(urls, strategy="random")
+        http_server.shutdown()
+        http_server.server_close()
+
     async def test_tcp_multipass(self):
         http_server, session1 = await make_http_and_session()
         session2 = await make_session()
@@ -682,6 +1152,143 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
         self.assertTrue(test_latency > 0)
         self.assertEqual(None, disconn_addr)
 
+    async def test_async_heartbeat_callback(self):
+        request_id = contextvars.ContextVar("request_id")
+        request_id.set("connect")
+        beat = asyncio.Event()
+        seen = []
+
+        async def on_heartbeat(latency):
+            seen.append((asyncio.get_running_loop(), request_id.get(None)))
+            beat.set()
+
+        builder = ngrok.SessionBuilder().authtoken_from_env()
+        session = await builder.handle_heartbeat(on_heartbeat).connect()
+        await asyncio.wait_for(beat.wait(), 30)
+        self.assertEqual((asyncio.get_running_loop(), "connect"), seen[0])
+        await session.close()
+
+    async def test_forward_from_config(self):
+        http_server = make_http()
+        port = http_server.server_address[1]
+        configs = [
+            f"""
+version: "2"
+tunnels:
+  web:
+    proto: http
+    addr: {port}
+    metadata: from-config
+""",
+            f"""
+version: 3
+endpoints:
+  - name: web
+    metadata: from-config
+    upstream:
+      url: {port}
+""",
+        ]
+        for config in configs:
+            with tempfile.NamedTemporaryFile("w", suffix=".yml") as f:
+                f.write(config)
+                f.flush()
+                listeners = await ngrok.forward_from_config(f.name)
+                ngrok.SessionBuilder.from_config(f.name)
+            self.assertEqual(1, len(listeners))
+            self.assertEqual("from-config", listeners[0].metadata())
+            await self.validate_http_request(listeners[0].url())
+            await listeners[0].close()
+
+        with tempfile.NamedTemporaryFile("w", suffix=".yml") as f:
+            f.write("version: 2\ntunnels:\n  web:\n    addr: 80\n")
+            f.flush()
+            with self.assertRaises(ngrok.ConfigError):
+                await ngrok.forward_from_config(f.name)
+        http_server.shutdown()
+        http_server.server_close()
+
+    async def test_listener_options(self):
+        options = ngrok.HttpOptions(
+            metadata="typed options",
+            basic_auth=("ngrok:online1line",),
+            circuit_breaker=1,
+        )
+        self.assertEqual(["ngrok:online1line"], options.basic_auth)
+        self.assertEqual(1.0, options.circuit_breaker)
+        self.assertEqual(options, ngrok.HttpOptions.from_json(options.to_json()))
+        self.assertEqual(options, ngrok.HttpOptions.from_dict(options.to_dict()))
+        self.assertEqual("http", options.to_dict()["proto"])
+
+        policy = ngrok.TrafficPolicy(
+            on_tcp_connect=[ngrok.PolicyRule([ngrok.PolicyAction("deny")])]
+        )
+        tcp = ngrok.TcpOptions(traffic_policy=policy)
+        self.assertEqual(policy.to_json(), tcp.traffic_policy)
+        self.assertEqual(tcp, ngrok.TcpOptions.from_json(tcp.to_json()))
+        tcp.traffic_policy = None
+        self.assertNotIn("traffic_policy", tcp.to_dict())
+
+        with self.assertRaises(ngrok.ConfigError):
+            ngrok.TcpOptions(domain="example.com")
+        with self.assertRaises(ngrok.ConfigError):
+            ngrok.HttpOptions.from_dict({"proto": "tcp"})
+
+        with open("examples/domain.crt", "r") as crt:
+            tls = ngrok.TlsOptions(mutual_tls_cas=crt.read())
+        self.assertEqual(tls, ngrok.TlsOptions.from_json(tls.to_json()))
+
+        http_server, session = await make_http_and_session()
+        listener = await options.apply(session.http_endpoint()).listen()
+        self.assertEqual("typed options", listener.metadata())
+        config = {"auth": ("ngrok", "online1line")}
+        await self.forward_validate_shutdown(
+            http_server, listener, listener.url(), config
+        )
+
+    async def test_builder_config(self):
+        http_server, session = await make_http_and_session()
+        base = (
+            session.http_endpoint()
+            .basic_auth("ngrok", "online1line")
+            .allow_cidr("0.0.0.0/0")
+            .webhook_verification("twilio", "asdf")
+        )
+        variant = base.copy().metadata("variant").allow_cidr("10.0.0.0/8")
+
+        config = base.config()
+        self.assertEqual(["ngrok:<redacted>"], config["basic_auth"])
+        self.assertEqual("<redacted>", config["verify_webhook_secret"])
+        self.assertEqual(["0.0.0.0/0"], config["allow_cidr"])
+        self.assertNotIn("metadata", config)
+        self.assertNotIn("online1line", repr(base))
+
+        policy = ngrok.TrafficPolicy(
+            on_http_request=[
+                ngrok.PolicyRule(
+                    [
+                        ngrok.PolicyAction(
+                            "basic-auth", {"credentials": ["ngrok:online1line"]}
+                        ),
+                        ngrok.PolicyAction(
+                            "verify-webhook", {"provider": "twilio", "secret": "asdf"}
+                        ),
+                    ]
+                )
+            ]
+        )
+        redacted = session.http_endpoint().traffic_policy(policy).config()
+        actions = json.loads(redacted["traffic_policy"])["on_http_request"][0]["actions"]
+        self.assertEqual(["ngrok:<redacted>"], actions[0]["config"]["credentials"])
+        self.assertEqual("<redacted>", actions[1]["config"]["secret"])
+        self.assertEqual("twilio", actions[1]["config"]["provider"])
+        self.assertEqual(["0.0.0.0/0", "10.0.0.0/8"], variant.config()["allow_cidr"])
+        self.assertTrue(repr(variant).startswith("HttpListenerBuilder("))
+
+        listener = await variant.listen()
+        self.assertEqual("variant", listener.metadata())
+        await shutdown(listener, http_server)
+
     async def test_ca_cert(self):
         error = None
         cert = None
@@ -702,6 +1309,11 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
             error = err
         self.assertIsInstance(error, ValueError)
         self.assertEqual("ERR_NGROK_105", error.args[2])
+        self.assertIsInstance(error, ngrok.AuthError)
+        self.assertIsInstance(error, ngrok.NgrokError)
+        self.assertEqual("ERR_NGROK_105", error.error_code)
+        self.assertIsNotNone(error.server_message)
+        self.assertEqual("failed to authenticate session", error.context)
 
     async def test_invalid_domain(self):
         session = await make_session()
@@ -712,6 +1324,29 @@ class TestNgrok(unittest.IsolatedAsyncioTestCase):
             error = err
         self.assertIsInstance(error, ValueError)
         self.assertEqual("ERR_NGROK_326", error.args[2])
+        self.assertIsInstance(error, ngrok.ListenerError)
+        self.assertEqual("ERR_NGROK_326", error.error_code)
+
+    async def test_config_error(self):
+        error = None
+        try:
+            ngrok.SessionBuilder().server_addr("not a valid address")
+        except ngrok.ConfigError as err:
+            error = err
+        self.assertIsInstance(error, ValueError)
+        self.assertIsNone(error.error_code)
+        self.assertTrue("not a valid address" in error.context)
+
+    async def test_reconnect_policy(self):
+        builder = ngrok.SessionBuilder()
+        self.assertIs(builder, builder.reconnect_policy(max_attempts=3))
+        with self.assertRaises(ngrok.ConfigError):
+            builder.reconnect_policy(jitter=2.0)
+        with self.assertRaises(ngrok.ConfigError):
+            builder.reconnect_policy(min_backoff=10.0, max_backoff=1.0)
+        session = await builder.authtoken_from_env().connect()
+        self.assertEqual("connected", session.state)
+        await session.close()
 
 
 if __name__ == "__main__":
@@ -1,6 +1,11 @@
 import ngrok
 import os
+import pathlib
 import requests
+import signal
+import subprocess
+import sys
+import textwrap
 import unittest
 import test
 from test import retry_request
@@ -156,6 +161,175 @@ class TestNgrokConnect(unittest.IsolatedAsyncioTestCase):
         response = self.validate_shutdown(http_server, listener, listener.url(), config)
         self.assertEqual("true2", response.headers["x-res-yup2"])
 
+    def test_forward_strict(self):
+        with self.assertRaises(ngrok.ConfigError) as ctx:
+            ngrok.forward(8080, authtoken_from_env=True, basic_auht="ngrok:online1line")
+        self.assertIn('did you mean "basic_auth"', str(ctx.exception))
+
+        with self.assertRaises(ngrok.ConfigError) as ctx:
+            ngrok.forward(8080, proto="tcp", domain="example.com")
+        self.assertIn('"domain" does not apply to proto "tcp"', str(ctx.exception))
+
+        http_server = test.make_http()
+        listener = ngrok.forward(
+            http_server.listen_to,
+            authtoken_from_env=True,
+            not_an_option=True,
+            strict=False,
+        )
+        self.validate_shutdown(http_server, listener, listener.url())
+
+    async def test_forward_coercion(self):
+        http_server = test.make_http()
+        listener = await ngrok.forward(
+            http_server.listen_to,
+            authtoken_from_env="true",
+            circuit_breaker=1,
+            compression="false",
+            schemes=("HTTPS",),
+        )
+        self.validate_shutdown(http_server, listener, listener.url())
+
+        with self.assertRaises(ngrok.ConfigError) as ctx:
+            await ngrok.forward(8080, authtoken_from_env=True, circuit_breaker="lots")
+        self.assertIn('"circuit_breaker" must be a number', str(ctx.exception))
+
+        with self.assertRaises(ngrok.ConfigError) as ctx:
+            await ngrok.forward(
+                8080,
+                authtoken_from_env=True,
+                mutual_tls_cas=pathlib.Path("/nonexistent/ca.crt"),
+            )
+        self.assertIn('"mutual_tls_cas" could not read', str(ctx.exception))
+
+    async def test_forward_many(self):
+        http_server = test.make_http()
+        listeners = await ngrok.forward_many(
+            [
+                {"addr": http_server.listen_to, "authtoken_from_env": True},
+                {"addr": http_server.listen_to, "metadata": "second"},
+            ]
+        )
+        self.assertEqual(2, len(listeners))
+        self.assertEqual("second", listeners[1].metadata())
+        for listener in listeners:
+            self.validate_http_request(listener.url())
+            await listener.close()
+
+        # one bad entry closes the listeners which did start
+        before = len(await ngrok.get_listeners())
+        with self.assertRaises(ngrok.ListenerError) as ctx:
+            await ngrok.forward_many(
+                [
+                    {"addr": http_server.listen_to, "authtoken_from_env": True},
+                    {"addr": http_server.listen_to, "proxy_proto": "bogus"},
+                ]
+            )
+        self.assertEqual(1, len(ctx.exception.errors))
+        self.assertIsInstance(ctx.exception.errors[0], ngrok.ConfigError)
+        self.assertEqual(before, len(await ngrok.get_listeners()))
+
+        # later dicts can't configure the shared session differently
+        with self.assertRaises(ngrok.ConfigError) as ctx:
+            await ngrok.forward_many(
+                [
+                    {"addr": http_server.listen_to, "authtoken_from_env": True},
+                    {"addr": http_server.listen_to, "session_metadata": "other"},
+                ]
+            )
+        self.assertIn('"session_metadata" in options [1]', str(ctx.exception))
+        self.assertEqual(before, len(await ngrok.get_listeners()))
+        http_server.shutdown()
+        http_server.server_close()
+
+    def test_install_signal_handlers(self):
+        script = textwrap.dedent(
+            """
+            import ngrok, time
+            listener = ngrok.forward(8080, authtoken_from_env=True)
+            ngrok.install_signal_handlers(drain_timeout=1)
+            print(listener.url(), flush=True)
+            time.sleep(60)
+            """
+        )
+        proc = subprocess.Popen(
+            [sys.executable, "-c", script], stdout=subprocess.PIPE, text=True
+        )
+        url = proc.stdout.readline().strip()
+        self.assertTrue(url.startswith("https://"), url)
+        proc.send_signal(signal.SIGTERM)
+        self.assertEqual(128 + signal.SIGTERM, proc.wait(timeout=30))
+        proc.stdout.close()
+
+    def test_exit_cleanup(self):
+        script = textwrap.dedent(
+            """
+            import logging, ngrok
+            logging.basicConfig(level=logging.DEBUG)
+            ngrok.log_level("DEBUG")
+            listener = ngrok.forward(8080, authtoken_from_env=True)
+            print(listener.url(), listener.id(), flush=True)
+            """
+        )
+        proc = subprocess.run(
+            [sys.executable, "-c", script], capture_output=True, text=True, timeout=30
+        )
+        self.assertEqual(0, proc.returncode, proc.stderr)
+        url, listener_id = proc.stdout.split()
+        self.assertTrue(url.startswith("https://"), proc.stdout)
+        # the finaliser closed the listener, and did so cleanly
+        self.assertIn(f'Listener closing, id: "{listener_id}"', proc.stderr)
+        self.assertNotIn("Error closing", proc.stderr)
+        self.assertNotIn("Timed out closing", proc.stderr)
+
+    def test_sync_timeout_cleanup(self):
+        script = textwrap.dedent(
+            """
+            import logging, ngrok, time
+            logging.basicConfig(level=logging.DEBUG)
+            ngrok.log_level("DEBUG")
+            session = ngrok.SessionBuilder().authtoken_from_env().connect_sync()
+            try:
+                session.http_endpoint().listen_sync(timeout=0.000001)
+            except TimeoutError:
+                print("timed out", flush=True)
+            # give the abandoned listen time to finish
+            time.sleep(5)
+            print(len(session.get_listeners()), flush=True)
+            """
+        )
+        proc = subprocess.run(
+            [sys.executable, "-c", script], capture_output=True, text=True, timeout=30
+        )
+        self.assertEqual(0, proc.returncode, proc.stderr)
+        self.assertEqual(["timed out", "0"], proc.stdout.split("\n")[:2])
+        # the listen carried on after the timeout, and its listener was closed
+        self.assertIn("which started after its listen timed out", proc.stderr)
+
+    def test_configure_runtime(self):
+        script = textwrap.dedent(
+            """
+            import ngrok
+            ngrok.configure_runtime(worker_threads=2, thread_name="ngrok-test")
+            assert not ngrok.runtime_info()["started"]
+            listener = ngrok.forward(8080, authtoken_from_env=True)
+            info = ngrok.runtime_info()
+            assert info["started"], info
+            assert info["worker_threads"] == 2, info
+            assert info["thread_name"] == "ngrok-test", info
+            assert info["alive_tasks"] > 0, info
+            try:
+                ngrok.configure_runtime(worker_threads=4)
+            except ngrok.ConfigError as e:
+                print(e, flush=True)
+            """
+        )
+        proc = subprocess.run(
+            [sys.executable, "-c", script], capture_output=True, text=True, timeout=30
+        )
+        self.assertEqual(0, proc.returncode, proc.stderr)
+        self.assertIn("already started", proc.stdout)
+
     async def test_tcp_listener(self):
         http_server = test.make_http()
         listener = await ngrok.forward(